@@ -0,0 +1,62 @@
+#ifndef PSYCHOQUINE_H
+#define PSYCHOQUINE_H
+
+/* Generated by cbindgen from core/src/ffi.rs; do not edit. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// A generated quine, freed with `pq_output_free`
+typedef struct PqOutput {
+  // One-line quine, NUL-terminated UTF-8
+  char *one_line;
+  // Multi-line quine, NUL-terminated UTF-8
+  char *multi_line;
+  // Input size in bytes
+  size_t input_bytes;
+  // One-line quine size in bytes
+  size_t one_line_bytes;
+  // Multi-line quine size in bytes
+  size_t multi_line_bytes;
+  // One-line size divided by input size
+  double expansion_ratio;
+} PqOutput;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Generate a quine from `input_len` bytes of UTF-8 text at `input`
+//
+// `options_json` is a JSON object such as `{"escape_strategy": "unicode"}`
+// with the keys of `psychoquine.toml` plus `style` and `seed`, or NULL
+// for the defaults. Returns NULL on failure; `pq_last_error` then says
+// why.
+//
+// # Safety
+//
+// `input` must point to `input_len` readable bytes (it may be NULL when
+// `input_len` is 0), and `options_json` must be NULL or a NUL-terminated
+// string.
+struct PqOutput *pq_generate(const uint8_t *input, size_t input_len, const char *options_json);
+
+// Free an output returned by `pq_generate`; NULL is ignored
+//
+// # Safety
+//
+// `output` must be NULL or a pointer returned by `pq_generate` that has
+// not been freed yet.
+void pq_output_free(struct PqOutput *output);
+
+// Why the last `pq_generate` on this thread failed, or NULL if it
+// succeeded
+//
+// The message is NUL-terminated UTF-8 owned by the library. It stays
+// valid until the next `pq_generate` call on the same thread.
+const char *pq_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* PSYCHOQUINE_H */
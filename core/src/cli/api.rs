@@ -0,0 +1,151 @@
+//! Calls shared by `psychoquine serve` and `psychoquine rpc`
+//!
+//! Parameters have the shapes the desktop app's commands take, so the same
+//! JSON works against the app, the HTTP API and JSON-RPC.
+
+use std::fmt;
+
+use serde::Deserialize;
+
+use psychoquine_core::{
+    extract, verify, verify_with_interpreter, Config, FormatOptions, GenerateRequest, Language,
+    ParseOptionError, QuineError, QuineGenerator, QuineOutput, VerifyMethod, VerifyReport,
+};
+
+/// JSON escaping can grow text up to six times (`\u0000`), so requests may
+/// be this many times the maximum input size, plus `SLACK` for the rest
+const REQUEST_FACTOR: usize = 6;
+const SLACK: usize = 64 * 1024;
+
+/// Parameters of `generate`, as the desktop app's `generate_quine` takes
+/// them
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GenerateParams {
+    pub input: String,
+    #[serde(default)]
+    pub options: GenerateRequest,
+}
+
+/// Parameters of `verify`, as the desktop app's `verify_quine` takes them
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VerifyParams {
+    pub source: String,
+    pub language: Option<String>,
+    /// `static` when not given; `runtime` needs `Engine::with_exec`
+    pub method: Option<VerifyMethod>,
+}
+
+/// Parameters of `extract`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExtractParams {
+    pub source: String,
+}
+
+/// Why a call failed
+#[derive(Debug)]
+pub enum CallError {
+    InvalidOption(ParseOptionError),
+    /// Runtime verification was asked for but running programs is off
+    ExecDisabled,
+    Quine(QuineError),
+}
+
+impl CallError {
+    /// Stable machine-readable name, as in `QuineError::kind`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CallError::InvalidOption(_) => "invalid_option",
+            CallError::ExecDisabled => "exec_disabled",
+            CallError::Quine(error) => error.kind(),
+        }
+    }
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallError::InvalidOption(error) => error.fmt(f),
+            CallError::ExecDisabled => f.write_str("runtime verification is disabled"),
+            CallError::Quine(error) => error.fmt(f),
+        }
+    }
+}
+
+impl From<ParseOptionError> for CallError {
+    fn from(error: ParseOptionError) -> Self {
+        CallError::InvalidOption(error)
+    }
+}
+
+impl From<QuineError> for CallError {
+    fn from(error: QuineError) -> Self {
+        CallError::Quine(error)
+    }
+}
+
+/// Answers calls with the defaults from `psychoquine.toml`
+pub struct Engine {
+    /// Options from `psychoquine.toml`, which requests are applied over
+    defaults: FormatOptions,
+    max_input_size: usize,
+    /// Whether `verify` may run programs with an interpreter
+    exec: bool,
+}
+
+impl Engine {
+    pub fn new(config: Config) -> Result<Self, QuineError> {
+        Ok(Self {
+            defaults: config.format_options()?,
+            max_input_size: config
+                .max_input_size
+                .unwrap_or(QuineGenerator::DEFAULT_MAX_INPUT_SIZE),
+            exec: false,
+        })
+    }
+
+    /// Allow `Runtime` verification, which runs the submitted program
+    pub fn with_exec(mut self, exec: bool) -> Self {
+        self.exec = exec;
+        self
+    }
+
+    /// Largest request worth reading: a maximum-size input, JSON-escaped
+    pub fn max_request_size(&self) -> usize {
+        self.max_input_size
+            .saturating_mul(REQUEST_FACTOR)
+            .saturating_add(SLACK)
+    }
+
+    /// Generate a quine; a request can lower the maximum input size but
+    /// not raise it
+    pub fn generate(&self, params: GenerateParams) -> Result<QuineOutput, CallError> {
+        let mut options = self.defaults.clone();
+        params.options.apply(&mut options)?;
+        let max_input_size = params
+            .options
+            .max_input_size
+            .map_or(self.max_input_size, |max| max.min(self.max_input_size));
+        let generator = QuineGenerator::with_options(options).with_max_input_size(max_input_size);
+        Ok(generator.generate(&params.input)?)
+    }
+
+    pub fn verify(&self, params: VerifyParams) -> Result<VerifyReport, CallError> {
+        let language = match params.language {
+            Some(name) => name.parse::<Language>()?,
+            None => Language::default(),
+        };
+        let report = match params.method.unwrap_or(VerifyMethod::Static) {
+            VerifyMethod::Static => verify(&params.source)?,
+            VerifyMethod::Runtime if !self.exec => return Err(CallError::ExecDisabled),
+            VerifyMethod::Runtime => verify_with_interpreter(&params.source, language)?,
+        };
+        Ok(report)
+    }
+
+    pub fn extract(&self, params: ExtractParams) -> Result<String, CallError> {
+        Ok(extract(&params.source)?)
+    }
+}
@@ -0,0 +1,321 @@
+//! Strict command-line parsing
+//!
+//! Each subcommand declares its options as a table of `OptSpec`s. The parser
+//! rejects anything not in the table, insists on values where an option
+//! takes one, and suggests the closest option name for typos.
+
+use super::error::CliError;
+
+/// One option a subcommand accepts
+///
+/// The tables double as the description of the CLI that help, the man
+/// page and shell completions are rendered from.
+#[derive(Debug, Clone, Copy)]
+pub struct OptSpec {
+    pub short: Option<char>,
+    pub long: &'static str,
+    /// Name of the option's value, or `None` for a flag
+    pub value: Option<&'static str>,
+    /// One-line description
+    pub help: &'static str,
+    /// Values the option accepts, when there is a fixed set
+    pub choices: Option<fn() -> Vec<&'static str>>,
+    /// Value used when the option is not given
+    pub default: Option<&'static str>,
+}
+
+impl OptSpec {
+    pub const fn flag(short: Option<char>, long: &'static str, help: &'static str) -> Self {
+        Self {
+            short,
+            long,
+            value: None,
+            help,
+            choices: None,
+            default: None,
+        }
+    }
+
+    pub const fn with_value(
+        short: Option<char>,
+        long: &'static str,
+        value: &'static str,
+        help: &'static str,
+    ) -> Self {
+        Self {
+            short,
+            long,
+            value: Some(value),
+            help,
+            choices: None,
+            default: None,
+        }
+    }
+
+    /// Restrict completion and help to a fixed set of values
+    pub const fn choices(mut self, choices: fn() -> Vec<&'static str>) -> Self {
+        self.choices = Some(choices);
+        self
+    }
+
+    /// Document the value used when the option is not given
+    pub const fn default(mut self, default: &'static str) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    /// `-e, --escape TYPE` or `    --style NAME`, for help
+    pub fn usage(&self) -> String {
+        let short = match self.short {
+            Some(short) => format!("-{}, ", short),
+            None => "    ".to_string(),
+        };
+        match self.value {
+            Some(value) => format!("{}--{} {}", short, self.long, value),
+            None => format!("{}--{}", short, self.long),
+        }
+    }
+
+    /// The help text with accepted values and default appended
+    pub fn description(&self) -> String {
+        let mut text = self.help.to_string();
+        if let Some(choices) = self.choices {
+            text.push_str(": ");
+            text.push_str(&choices().join(", "));
+        }
+        if let Some(default) = self.default {
+            text.push_str(&format!(" (default: {})", default));
+        }
+        text
+    }
+
+    /// `-e/--escape` or `--escape`, for diagnostics
+    fn display(&self) -> String {
+        match self.short {
+            Some(short) => format!("-{}/--{}", short, self.long),
+            None => format!("--{}", self.long),
+        }
+    }
+}
+
+/// A parsed argument
+#[derive(Debug, PartialEq, Eq)]
+pub enum Arg {
+    /// An option, by long name, with its value if it takes one
+    Opt(&'static str, Option<String>),
+    Positional(String),
+}
+
+/// Parse arguments against a subcommand's option table
+///
+/// Accepts `--long value`, `--long=value`, `-s value`, `-svalue` and
+/// bundled short flags (`-oq`). Everything after `--` is positional, as
+/// is a lone `-`.
+pub fn parse(argv: &[String], specs: &[OptSpec]) -> Result<Vec<Arg>, CliError> {
+    let mut args = Vec::new();
+    let mut rest = argv.iter();
+
+    while let Some(arg) = rest.next() {
+        if arg == "--" {
+            args.extend(rest.by_ref().cloned().map(Arg::Positional));
+            break;
+        }
+
+        if let Some(long) = arg.strip_prefix("--") {
+            let (name, inline) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (long, None),
+            };
+            let spec = specs
+                .iter()
+                .find(|spec| spec.long == name)
+                .ok_or_else(|| unknown_option(arg, name, specs))?;
+            let value = match (spec.value, inline) {
+                (None, None) => None,
+                (None, Some(_)) => {
+                    return Err(CliError::Usage(format!(
+                        "option {} does not take a value",
+                        spec.display()
+                    )))
+                }
+                (Some(_), Some(value)) => Some(value),
+                (Some(_), None) => Some(take_value(spec, rest.next())?),
+            };
+            args.push(Arg::Opt(spec.long, value));
+        } else if arg.len() > 1 && arg.starts_with('-') {
+            for (i, short) in arg[1..].char_indices() {
+                let spec = specs
+                    .iter()
+                    .find(|spec| spec.short == Some(short))
+                    .ok_or_else(|| match arg.chars().count() {
+                        2 => CliError::Usage(format!("unknown option '{}'", arg)),
+                        _ => CliError::Usage(format!("unknown option '-{}' in '{}'", short, arg)),
+                    })?;
+                if spec.value.is_none() {
+                    args.push(Arg::Opt(spec.long, None));
+                    continue;
+                }
+                // The rest of the bundle, or the next argument, is the value
+                let attached = &arg[1 + i + short.len_utf8()..];
+                let value = if attached.is_empty() {
+                    take_value(spec, rest.next())?
+                } else {
+                    attached.to_string()
+                };
+                args.push(Arg::Opt(spec.long, Some(value)));
+                break;
+            }
+        } else {
+            args.push(Arg::Positional(arg.clone()));
+        }
+    }
+
+    Ok(args)
+}
+
+/// The value following an option, which must not look like another option
+fn take_value(spec: &OptSpec, next: Option<&String>) -> Result<String, CliError> {
+    match next {
+        Some(value) if value == "-" || !value.starts_with('-') => Ok(value.clone()),
+        _ => Err(CliError::Usage(format!(
+            "option {} requires a value: {}",
+            spec.display(),
+            spec.value.unwrap_or("VALUE")
+        ))),
+    }
+}
+
+fn unknown_option(arg: &str, name: &str, specs: &[OptSpec]) -> CliError {
+    match closest(name, specs.iter().map(|spec| spec.long)) {
+        Some(long) => CliError::Usage(format!(
+            "unknown option '{}' (did you mean '--{}'?)",
+            arg, long
+        )),
+        None => CliError::Usage(format!("unknown option '{}'", arg)),
+    }
+}
+
+/// The candidate closest to a misspelled `name`, if any is close enough
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= 2)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between two short strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Parse a value from a fixed set of names, listing them on failure
+pub fn choice<T>(
+    option: &str,
+    value: &str,
+    names: &[&str],
+    lookup: impl Fn(&str) -> Option<T>,
+) -> Result<T, CliError> {
+    lookup(value).ok_or_else(|| {
+        CliError::Usage(format!(
+            "invalid value '{}' for {} (expected one of: {})",
+            value,
+            option,
+            names.join(", ")
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPECS: &[OptSpec] = &[
+        OptSpec::flag(Some('o'), "one-line", ""),
+        OptSpec::flag(Some('q'), "quiet", ""),
+        OptSpec::with_value(Some('e'), "escape", "TYPE", ""),
+    ];
+
+    fn argv(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn usage_message(result: Result<Vec<Arg>, CliError>) -> String {
+        match result {
+            Err(CliError::Usage(message)) => message,
+            other => panic!("expected a usage error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_forms() {
+        let parsed = parse(
+            &argv(&[
+                "-oq",
+                "--escape=hex",
+                "-eunicode",
+                "-e",
+                "raw",
+                "text",
+                "--",
+                "-o",
+            ]),
+            SPECS,
+        )
+        .unwrap();
+        assert_eq!(
+            parsed,
+            vec![
+                Arg::Opt("one-line", None),
+                Arg::Opt("quiet", None),
+                Arg::Opt("escape", Some("hex".to_string())),
+                Arg::Opt("escape", Some("unicode".to_string())),
+                Arg::Opt("escape", Some("raw".to_string())),
+                Arg::Positional("text".to_string()),
+                Arg::Positional("-o".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_value() {
+        let message = usage_message(parse(&argv(&["-e"]), SPECS));
+        assert!(message.contains("requires a value"), "{}", message);
+        let message = usage_message(parse(&argv(&["-e", "-o"]), SPECS));
+        assert!(message.contains("-e/--escape"), "{}", message);
+    }
+
+    #[test]
+    fn test_unknown_options() {
+        let message = usage_message(parse(&argv(&["--escpe", "hex"]), SPECS));
+        assert!(message.contains("did you mean '--escape'"), "{}", message);
+        let message = usage_message(parse(&argv(&["-x"]), SPECS));
+        assert!(message.contains("'-x'"), "{}", message);
+        let message = usage_message(parse(&argv(&["--quiet=yes"]), SPECS));
+        assert!(message.contains("does not take a value"), "{}", message);
+    }
+
+    #[test]
+    fn test_choice() {
+        let names = ["a", "b"];
+        let lookup = |name: &str| names.iter().position(|n| *n == name);
+        assert_eq!(choice("--x", "b", &names, lookup).unwrap(), 1);
+        let message = match choice("--x", "c", &names, lookup) {
+            Err(CliError::Usage(message)) => message,
+            _ => unreachable!(),
+        };
+        assert_eq!(message, "invalid value 'c' for --x (expected one of: a, b)");
+    }
+}
@@ -0,0 +1,492 @@
+//! `psychoquine batch`: generate quines for every file matching a pattern
+//!
+//! Files are processed by a pool of worker threads. Results are written
+//! next to each input as `<stem>.quine.<ext>`, or as `<stem>.<ext>` in
+//! `--out-dir`, and a summary of the whole run is printed at the end.
+
+use std::fs;
+use std::io::{self, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use serde::Serialize;
+use serde_json::json;
+
+use psychoquine_core::{OutputFormat, QuineOutput, QuineStats};
+
+use super::args::{self, Arg, OptSpec};
+use super::commands;
+use super::error::CliError;
+use super::options::{Generation, GenerationFlags, GENERATION_OPTIONS};
+use super::output::{self, named_outputs};
+use super::report::{self, ErrorRecord, Format, GolfSummary, Record, SCHEMA_VERSION};
+
+/// Stem suffix of quines written next to their inputs
+const QUINE_SUFFIX: &str = ".quine";
+
+pub const OPTIONS: &[OptSpec] = &[
+    OptSpec::flag(Some('h'), "help", "Show this help message"),
+    OptSpec::with_value(Some('j'), "jobs", "N", "Number of worker threads").default("CPU count"),
+    OptSpec::with_value(
+        None,
+        "out-dir",
+        "DIR",
+        "Write quines into DIR instead of next to the inputs",
+    ),
+    OptSpec::with_value(None, "format", "FMT", "Output format")
+        .choices(report::format_names)
+        .default("text"),
+    OptSpec::flag(Some('q'), "quiet", "Only print the summary"),
+];
+
+#[derive(Default)]
+struct Args {
+    help: bool,
+    quiet: bool,
+    generation: GenerationFlags,
+    jobs: Option<String>,
+    out_dir: Option<String>,
+    format: Option<String>,
+    patterns: Vec<String>,
+}
+
+fn parse_args(argv: &[String]) -> Result<Args, CliError> {
+    let mut args = Args::default();
+
+    for arg in args::parse(argv, &[GENERATION_OPTIONS, OPTIONS].concat())? {
+        if args.generation.accept(&arg) {
+            continue;
+        }
+        match arg {
+            Arg::Opt("help", _) => args.help = true,
+            Arg::Opt("quiet", _) => args.quiet = true,
+            Arg::Opt("jobs", value) => args.jobs = value,
+            Arg::Opt("out-dir", value) => args.out_dir = value,
+            Arg::Opt("format", value) => args.format = value,
+            Arg::Opt(other, _) => unreachable!("option --{} has no handler", other),
+            Arg::Positional(pattern) => args.patterns.push(pattern),
+        }
+    }
+
+    Ok(args)
+}
+
+/// Run `psychoquine batch`
+pub fn run(argv: &[String]) -> ExitCode {
+    let args = match parse_args(argv) {
+        Ok(args) => args,
+        Err(e) => return e.report(),
+    };
+
+    if args.help {
+        commands::print_help("batch");
+        return ExitCode::SUCCESS;
+    }
+
+    let format = match report::parse_format(args.format.as_deref()) {
+        Ok(format) => format,
+        Err(e) => return e.report(),
+    };
+    match plan(&args) {
+        Ok((generation, jobs, workers)) => execute(&args, format, &generation, &jobs, workers),
+        Err(e) => report::fail(format, &e),
+    }
+}
+
+/// One input file and the files its quines go to
+pub struct Job {
+    pub input: PathBuf,
+    pub outputs: Vec<(OutputFormat, PathBuf)>,
+}
+
+/// Check options, expand patterns and decide every output path up front
+fn plan(args: &Args) -> Result<(Generation, Vec<Job>, usize), CliError> {
+    if args.patterns.is_empty() {
+        return Err(CliError::Usage(
+            "missing PATTERN\nUsage: psychoquine batch [OPTIONS] PATTERN...".to_string(),
+        ));
+    }
+
+    let workers = match args.jobs.as_deref() {
+        Some(jobs) => jobs
+            .parse::<NonZeroUsize>()
+            .map_err(|_| {
+                CliError::Usage(format!(
+                    "invalid value '{}' for --jobs (expected a positive integer)",
+                    jobs
+                ))
+            })?
+            .get(),
+        None => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+    };
+
+    let generation = args.generation.resolve()?;
+    let jobs = jobs(
+        expand(&args.patterns)?,
+        args.out_dir.as_deref(),
+        &generation,
+    )?;
+    if jobs.is_empty() {
+        return Err(CliError::Usage(
+            "the patterns match no input files".to_string(),
+        ));
+    }
+
+    let workers = workers.min(jobs.len());
+    Ok((generation, jobs, workers))
+}
+
+/// Decide where each input's quines go, creating `out_dir` if given
+///
+/// Fails if two inputs would write the same file, or if a quine would be
+/// written over one of the inputs.
+pub fn jobs(
+    inputs: Vec<PathBuf>,
+    out_dir: Option<&str>,
+    generation: &Generation,
+) -> Result<Vec<Job>, CliError> {
+    let extension = generation.language.extension();
+    let resolved_inputs: Vec<(PathBuf, PathBuf)> = inputs
+        .iter()
+        .map(|input| (resolve(input), input.clone()))
+        .collect();
+
+    let mut jobs: Vec<Job> = Vec::new();
+    for input in inputs {
+        let stem = output::file_stem(&input);
+        let outputs = match out_dir {
+            Some(dir) => named_outputs(Path::new(dir), stem, extension, generation.which),
+            None => {
+                let dir = input.parent().unwrap_or(Path::new(""));
+                let stem = format!("{}{}", stem, QUINE_SUFFIX);
+                named_outputs(dir, &stem, extension, generation.which)
+            }
+        };
+
+        for (_, path) in &outputs {
+            let resolved = resolve(path);
+            if let Some((_, overwritten)) = resolved_inputs
+                .iter()
+                .find(|(resolved_input, _)| *resolved_input == resolved)
+            {
+                return Err(CliError::Usage(format!(
+                    "the quine of {} would overwrite the input {}",
+                    input.display(),
+                    overwritten.display()
+                )));
+            }
+            // Two inputs with the same stem would overwrite each other
+            if let Some(other) = jobs
+                .iter()
+                .find(|job| job.outputs.iter().any(|(_, existing)| existing == path))
+            {
+                return Err(CliError::Usage(format!(
+                    "{} and {} would both be written to {}",
+                    other.input.display(),
+                    input.display(),
+                    path.display()
+                )));
+            }
+        }
+        jobs.push(Job { input, outputs });
+    }
+
+    if let Some(dir) = out_dir {
+        fs::create_dir_all(dir).map_err(|e| CliError::io(format!("cannot create {}", dir), e))?;
+    }
+
+    Ok(jobs)
+}
+
+/// `path` with symbolic links and `.`/`..` resolved, as far as it exists
+///
+/// An output usually doesn't exist yet, so its directory is resolved
+/// instead; a path in a directory that doesn't exist can't be an input.
+fn resolve(path: &Path) -> PathBuf {
+    if let Ok(path) = fs::canonicalize(path) {
+        return path;
+    }
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    match (fs::canonicalize(dir), path.file_name()) {
+        (Ok(dir), Some(name)) => dir.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Files matching the patterns, in order and without duplicates
+///
+/// Directories and quines written by an earlier batch run are skipped.
+fn expand(patterns: &[String]) -> Result<Vec<PathBuf>, CliError> {
+    let mut inputs: Vec<PathBuf> = Vec::new();
+    for pattern in patterns {
+        let paths = glob::glob(pattern)
+            .map_err(|e| CliError::Usage(format!("invalid pattern '{}': {}", pattern, e.msg)))?;
+
+        let mut matched = false;
+        for entry in paths {
+            let path = entry.map_err(|e| {
+                let context = format!("cannot read {}", e.path().display());
+                CliError::io(context, e.into())
+            })?;
+            matched = true;
+            if path.is_file() && !is_batch_output(&path) && !inputs.contains(&path) {
+                inputs.push(path);
+            }
+        }
+        if !matched {
+            return Err(CliError::Usage(format!("no files match '{}'", pattern)));
+        }
+    }
+    Ok(inputs)
+}
+
+/// Whether `path` looks like `<stem>.quine.<ext>` or `<stem>.quine.min.<ext>`
+fn is_batch_output(path: &Path) -> bool {
+    let stem = output::file_stem(path);
+    let stem = stem.strip_suffix(".min").unwrap_or(stem);
+    stem.ends_with(QUINE_SUFFIX)
+}
+
+/// What processing one input produced
+pub type Outcome = Result<(QuineOutput, Option<GolfSummary>), CliError>;
+
+/// Read one input, generate its quines and write them
+pub fn process(generation: &Generation, job: &Job) -> Outcome {
+    let text = super::read_file(&job.input, generation.max_input_size)?;
+
+    let (output, golf) = generation.generate(&text)?;
+    for (which, path) in &job.outputs {
+        fs::write(path, output.source(*which))
+            .map_err(|e| CliError::io(format!("cannot write {}", path.display()), e))?;
+    }
+    Ok((output, golf))
+}
+
+/// Aggregate statistics of a batch run
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct Summary {
+    files: usize,
+    succeeded: usize,
+    failed: usize,
+    input_bytes: usize,
+    one_line_bytes: usize,
+    multi_line_bytes: usize,
+    /// Mean of the per-file expansion ratios; `None` when nothing succeeded
+    mean_expansion_ratio: Option<f64>,
+}
+
+impl Summary {
+    fn add_success(&mut self, stats: &QuineStats) {
+        let total = self.mean_expansion_ratio.unwrap_or(0.0) * self.succeeded as f64;
+        self.files += 1;
+        self.succeeded += 1;
+        self.input_bytes += stats.input_bytes;
+        self.one_line_bytes += stats.one_line_bytes;
+        self.multi_line_bytes += stats.multi_line_bytes;
+        self.mean_expansion_ratio = Some((total + stats.expansion_ratio) / self.succeeded as f64);
+    }
+
+    fn add_failure(&mut self) {
+        self.files += 1;
+        self.failed += 1;
+    }
+}
+
+/// Run the worker pool and report results as they arrive
+fn execute(
+    args: &Args,
+    format: Format,
+    generation: &Generation,
+    jobs: &[Job],
+    workers: usize,
+) -> ExitCode {
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    let mut summary = Summary::default();
+    let mut records: Vec<(usize, Record)> = Vec::new();
+    // Failures by input index, so they can be listed in input order
+    let mut failures: Vec<(usize, CliError)> = Vec::new();
+
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel::<(usize, Outcome)>();
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let sender = sender.clone();
+            let next = &next;
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(job) = jobs.get(index) else {
+                    break;
+                };
+                if sender.send((index, process(generation, job))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        for (index, outcome) in receiver {
+            let job = &jobs[index];
+            let input = Some(job.input.display().to_string());
+            let record = match outcome {
+                Ok((output, golf)) => {
+                    summary.add_success(&output.stats);
+                    if format == Format::Text && !args.quiet {
+                        for (_, path) in &job.outputs {
+                            eprintln!("Wrote {}", path.display());
+                        }
+                    }
+                    let mut record = Record::success(input, output);
+                    record.golf = golf;
+                    record.files = job
+                        .outputs
+                        .iter()
+                        .map(|(_, path)| path.display().to_string())
+                        .collect();
+                    record
+                }
+                Err(error) => {
+                    summary.add_failure();
+                    if format == Format::Text && !args.quiet {
+                        eprintln!("error: {}: {}", job.input.display(), error);
+                    }
+                    let record = Record::failure(input, ErrorRecord::from(&error));
+                    failures.push((index, error));
+                    record
+                }
+            };
+            match format {
+                Format::Ndjson => {
+                    writeln!(handle, "{}", record.to_line()).ok();
+                }
+                Format::Json => records.push((index, record)),
+                Format::Text => {}
+            }
+        }
+    });
+
+    failures.sort_by_key(|(index, _)| *index);
+    records.sort_by_key(|(index, _)| *index);
+
+    match format {
+        Format::Text => print_summary(&mut handle, &summary, jobs, &failures).ok(),
+        Format::Json => {
+            let results: Vec<Record> = records.into_iter().map(|(_, record)| record).collect();
+            let document = json!({
+                "schema_version": SCHEMA_VERSION,
+                "results": results,
+                "summary": summary,
+            });
+            writeln!(handle, "{:#}", document).ok()
+        }
+        Format::Ndjson => {
+            let line = json!({ "schema_version": SCHEMA_VERSION, "summary": summary });
+            writeln!(handle, "{}", line).ok()
+        }
+    };
+
+    match failures.first() {
+        Some((_, error)) => ExitCode::from(error.exit_code()),
+        None => ExitCode::SUCCESS,
+    }
+}
+
+fn print_summary<W: Write>(
+    handle: &mut W,
+    summary: &Summary,
+    jobs: &[Job],
+    failures: &[(usize, CliError)],
+) -> io::Result<()> {
+    writeln!(handle, "═══ BATCH SUMMARY ═══")?;
+    writeln!(handle, "Files:            {}", summary.files)?;
+    writeln!(handle, "Succeeded:        {}", summary.succeeded)?;
+    writeln!(handle, "Failed:           {}", summary.failed)?;
+    writeln!(handle, "Input size:       {} bytes", summary.input_bytes)?;
+    writeln!(handle, "One-line size:    {} bytes", summary.one_line_bytes)?;
+    writeln!(
+        handle,
+        "Multi-line size:  {} bytes",
+        summary.multi_line_bytes
+    )?;
+    match summary.mean_expansion_ratio {
+        Some(ratio) => writeln!(handle, "Mean expansion:   {:.2}x", ratio)?,
+        None => writeln!(handle, "Mean expansion:   n/a")?,
+    }
+    if !failures.is_empty() {
+        writeln!(handle)?;
+        writeln!(handle, "═══ FAILURES ═══")?;
+        for (index, error) in failures {
+            writeln!(handle, "{}: {}", jobs[*index].input.display(), error)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_batch_output() {
+        assert!(is_batch_output(Path::new("docs/intro.quine.js")));
+        assert!(is_batch_output(Path::new("intro.quine.min.js")));
+        assert!(!is_batch_output(Path::new("intro.txt")));
+        assert!(!is_batch_output(Path::new("quine.js")));
+    }
+
+    #[test]
+    fn test_outputs_never_overwrite_inputs() {
+        let dir = std::env::temp_dir().join(format!("psychoquine-batch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["b.txt", "b.js", "c.txt"] {
+            fs::write(dir.join(name), "x").unwrap();
+        }
+        let mut flags = GenerationFlags::default();
+        flags.accept(&args::Arg::Opt("no-config", None));
+        let generation = flags.resolve().unwrap();
+        let out_dir = format!("{}/./", dir.display());
+
+        // b.txt's quine would replace the input b.js
+        let inputs = vec![dir.join("b.txt"), dir.join("b.js")];
+        let error = jobs(inputs, Some(&out_dir), &generation).err().unwrap();
+        assert!(
+            matches!(&error, CliError::Usage(message) if message.contains("overwrite the input")),
+            "{}",
+            error
+        );
+        // So would b.js's own quine
+        assert!(jobs(vec![dir.join("b.js")], Some(&out_dir), &generation).is_err());
+
+        let jobs = jobs(vec![dir.join("c.txt")], Some(&out_dir), &generation).unwrap();
+        let outputs: Vec<&PathBuf> = jobs[0].outputs.iter().map(|(_, path)| path).collect();
+        assert_eq!(outputs, [&dir.join("./c.min.js"), &dir.join("./c.js")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_summary_means_expansion_ratio() {
+        let mut summary = Summary::default();
+        summary.add_failure();
+        assert_eq!(summary.mean_expansion_ratio, None);
+
+        for input in ["a", "a longer input"] {
+            let output = psychoquine_core::generate(input).unwrap();
+            summary.add_success(&output.stats);
+        }
+        let a = psychoquine_core::generate("a").unwrap().stats;
+        let b = psychoquine_core::generate("a longer input").unwrap().stats;
+        assert_eq!(summary.files, 3);
+        assert_eq!(summary.succeeded, 2);
+        assert_eq!(summary.failed, 1);
+        assert_eq!(summary.input_bytes, a.input_bytes + b.input_bytes);
+        let mean = summary.mean_expansion_ratio.unwrap();
+        assert!((mean - (a.expansion_ratio + b.expansion_ratio) / 2.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,257 @@
+//! Declarative description of the CLI
+//!
+//! Dispatch, `--help`, the man page and shell completions are all rendered
+//! from `COMMANDS` and the subcommands' option tables, so adding an option
+//! or subcommand in one place updates every one of them.
+
+use std::process::ExitCode;
+
+use super::args::OptSpec;
+use super::error::EXIT_CODES;
+use super::options::GENERATION_OPTIONS;
+use super::{batch, completions, generate, inspect, man, repl, rpc, serve, templates, watch};
+
+/// Options of subcommands that take nothing but `--help`
+pub const HELP_OPTIONS: &[OptSpec] = &[OptSpec::flag(Some('h'), "help", "Show this help message")];
+
+/// What a command's positional arguments are, for completion
+#[derive(Debug, Clone, Copy)]
+pub enum Positional {
+    /// Free text, or no arguments at all
+    Text,
+    Files,
+    /// One of a fixed set of values
+    Choices(fn() -> Vec<&'static str>),
+}
+
+/// One subcommand
+pub struct Command {
+    pub name: &'static str,
+    /// Positional arguments, as shown in usage
+    pub args: &'static str,
+    pub about: &'static str,
+    /// Option tables, in the order they are listed in help
+    pub options: &'static [&'static [OptSpec]],
+    pub positional: Positional,
+    pub run: fn(&[String]) -> ExitCode,
+}
+
+impl Command {
+    /// `psychoquine batch [OPTIONS] PATTERN...`
+    pub fn usage(&self) -> String {
+        let mut usage = format!("psychoquine {} [OPTIONS]", self.name);
+        if !self.args.is_empty() {
+            usage.push(' ');
+            usage.push_str(self.args);
+        }
+        usage
+    }
+
+    /// Every option the command accepts
+    pub fn all_options(&self) -> impl Iterator<Item = &'static OptSpec> {
+        self.options.iter().flat_map(|table| table.iter())
+    }
+
+    /// Help for `psychoquine <command> --help`
+    pub fn help(&self) -> String {
+        let mut help = format!("Usage: {}\n\n{}\n\nOPTIONS:\n", self.usage(), self.about);
+        help.push_str(&option_rows(self.all_options()));
+        help
+    }
+}
+
+/// The default subcommand
+pub const GENERATE: &Command = &COMMANDS[0];
+
+/// Every subcommand, the default first
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "generate",
+        args: "[INPUT]",
+        about: "Generate quines from input (default)",
+        options: &[GENERATION_OPTIONS, generate::OPTIONS],
+        positional: Positional::Text,
+        run: generate::run,
+    },
+    Command {
+        name: "verify",
+        args: "FILE",
+        about: "Run a quine and check that it prints itself",
+        options: &[inspect::VERIFY_OPTIONS],
+        positional: Positional::Files,
+        run: inspect::verify,
+    },
+    Command {
+        name: "extract",
+        args: "FILE",
+        about: "Recover the payload embedded in a quine",
+        options: &[HELP_OPTIONS],
+        positional: Positional::Files,
+        run: inspect::extract,
+    },
+    Command {
+        name: "explain",
+        args: "FILE",
+        about: "Describe how a quine reproduces itself",
+        options: &[HELP_OPTIONS],
+        positional: Positional::Files,
+        run: inspect::explain,
+    },
+    Command {
+        name: "templates",
+        args: "",
+        about: "List languages, escape strategies and styles",
+        options: &[HELP_OPTIONS],
+        positional: Positional::Text,
+        run: templates::run,
+    },
+    Command {
+        name: "batch",
+        args: "PATTERN...",
+        about: "Generate quines for every file matching the glob PATTERNs",
+        options: &[GENERATION_OPTIONS, batch::OPTIONS],
+        positional: Positional::Files,
+        run: batch::run,
+    },
+    Command {
+        name: "watch",
+        args: "PATH...",
+        about: "Regenerate quines whenever the input files change",
+        options: &[GENERATION_OPTIONS, watch::OPTIONS],
+        positional: Positional::Files,
+        run: watch::run,
+    },
+    Command {
+        name: "repl",
+        args: "",
+        about: "Turn lines into quines interactively",
+        options: &[GENERATION_OPTIONS, HELP_OPTIONS],
+        positional: Positional::Text,
+        run: repl::run,
+    },
+    Command {
+        name: "serve",
+        args: "",
+        about: "Serve generation and verification as a JSON HTTP API",
+        options: &[serve::OPTIONS],
+        positional: Positional::Text,
+        run: serve::run,
+    },
+    Command {
+        name: "rpc",
+        args: "",
+        about: "Answer JSON-RPC requests, one per line, on stdin and stdout",
+        options: &[HELP_OPTIONS],
+        positional: Positional::Text,
+        run: rpc::run,
+    },
+    Command {
+        name: "completions",
+        args: "SHELL",
+        about: "Print a shell completion script",
+        options: &[HELP_OPTIONS],
+        positional: Positional::Choices(completions::shell_names),
+        run: completions::run,
+    },
+    Command {
+        name: "man",
+        args: "",
+        about: "Print the man page in roff format",
+        options: &[HELP_OPTIONS],
+        positional: Positional::Text,
+        run: man::run,
+    },
+];
+
+/// Look up a subcommand by name
+pub fn find(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|command| command.name == name)
+}
+
+/// Print `help` for the named command to stderr
+pub fn print_help(name: &str) {
+    let command = find(name).expect("command is described");
+    eprint!("{}", command.help());
+}
+
+/// Aligned `-e, --escape TYPE   Escape strategy: ...` lines
+fn option_rows<'a>(options: impl Iterator<Item = &'a OptSpec>) -> String {
+    options
+        .map(|spec| format!("    {:<19} {}\n", spec.usage(), spec.description()))
+        .collect()
+}
+
+/// Top-level help: subcommands, generate's options and exit codes
+pub fn main_help() -> String {
+    let mut help = String::from("USAGE:\n");
+    help.push_str("    psychoquine [generate] [OPTIONS] [INPUT]\n");
+    help.push_str("    echo \"text\" | psychoquine [OPTIONS]\n");
+    help.push_str("    psychoquine <COMMAND> [ARGS]\n\nCOMMANDS:\n");
+    for command in COMMANDS {
+        let name = format!("{} {}", command.name, command.args);
+        help.push_str(&format!("    {:<19} {}\n", name.trim_end(), command.about));
+    }
+    help.push_str("\n    FILE may be - to read from stdin. Run 'psychoquine COMMAND --help'\n");
+    help.push_str("    for the options of each command.\n\nOPTIONS:\n");
+    help.push_str(&option_rows(GENERATE.all_options()));
+    help.push_str("\nEXIT STATUS:\n");
+    for (code, meaning) in EXIT_CODES {
+        help.push_str(&format!("    {:<4}{}\n", code, meaning));
+    }
+    help
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use super::super::args::{self, Arg};
+
+    #[test]
+    fn test_described_options_parse() {
+        for command in COMMANDS {
+            let specs: Vec<OptSpec> = command.all_options().copied().collect();
+            for (i, spec) in specs.iter().enumerate() {
+                assert!(
+                    specs[..i].iter().all(|other| other.long != spec.long
+                        && (spec.short.is_none() || other.short != spec.short)),
+                    "{}: --{} is declared twice",
+                    command.name,
+                    spec.long
+                );
+
+                let mut argv = vec![format!("--{}", spec.long)];
+                if let Some(choices) = spec.choices {
+                    argv.push(choices()[0].to_string());
+                } else if spec.value.is_some() {
+                    argv.push("1".to_string());
+                }
+                let parsed = args::parse(&argv, &specs).unwrap();
+                assert!(
+                    matches!(parsed[0], Arg::Opt(long, _) if long == spec.long),
+                    "{} --{}",
+                    command.name,
+                    spec.long
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_help_lists_every_option() {
+        for command in COMMANDS {
+            let help = command.help();
+            for spec in command.all_options() {
+                assert!(
+                    help.contains(&spec.usage()),
+                    "{}: {}",
+                    command.name,
+                    spec.long
+                );
+            }
+        }
+        let help = main_help();
+        assert!(help.contains("--escape TYPE   Escape strategy: standard, unicode"));
+        assert!(COMMANDS.iter().all(|command| help.contains(command.about)));
+    }
+}
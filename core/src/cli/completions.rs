@@ -0,0 +1,354 @@
+//! `psychoquine completions`: shell completion scripts
+//!
+//! Scripts are rendered from the command table, so they complete exactly
+//! the subcommands and options the parser accepts.
+
+use std::process::ExitCode;
+
+use super::args::{self, choice, Arg, OptSpec};
+use super::commands::{self, Command, Positional, COMMANDS, HELP_OPTIONS};
+use super::error::CliError;
+
+/// Shells completion scripts are available for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            _ => None,
+        }
+    }
+}
+
+/// Names accepted for `SHELL`
+pub fn shell_names() -> Vec<&'static str> {
+    vec!["bash", "zsh", "fish"]
+}
+
+/// Run `psychoquine completions`
+pub fn run(argv: &[String]) -> ExitCode {
+    match completions(argv) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => e.report(),
+    }
+}
+
+fn completions(argv: &[String]) -> Result<(), CliError> {
+    let mut shell = None;
+    for arg in args::parse(argv, HELP_OPTIONS)? {
+        match arg {
+            Arg::Opt("help", _) => {
+                commands::print_help("completions");
+                return Ok(());
+            }
+            Arg::Opt(other, _) => unreachable!("option --{} has no handler", other),
+            Arg::Positional(name) if shell.is_none() => {
+                shell = Some(choice("SHELL", &name, &shell_names(), Shell::from_name)?)
+            }
+            Arg::Positional(name) => {
+                return Err(CliError::Usage(format!("unexpected argument '{}'", name)))
+            }
+        }
+    }
+
+    let shell = shell.ok_or_else(|| {
+        CliError::Usage("missing SHELL\nUsage: psychoquine completions bash|zsh|fish".to_string())
+    })?;
+    print!("{}", render(shell));
+    Ok(())
+}
+
+/// The completion script for `shell`
+pub fn render(shell: Shell) -> String {
+    match shell {
+        Shell::Bash => bash(),
+        Shell::Zsh => zsh(),
+        Shell::Fish => fish(),
+    }
+}
+
+/// What an option's value completes to
+enum Values {
+    Nothing,
+    Files,
+    Directories,
+    Choices(Vec<&'static str>),
+}
+
+fn values(spec: &OptSpec) -> Values {
+    match (spec.value, spec.choices) {
+        (_, Some(choices)) => Values::Choices(choices()),
+        (Some("DIR"), None) => Values::Directories,
+        (Some("PATH" | "FILE"), None) => Values::Files,
+        _ => Values::Nothing,
+    }
+}
+
+/// `-e --escape`, or `--style` for an option without a short name
+fn spellings(spec: &OptSpec) -> Vec<String> {
+    let mut names: Vec<String> = spec.short.map(|c| format!("-{}", c)).into_iter().collect();
+    names.push(format!("--{}", spec.long));
+    names
+}
+
+fn command_names() -> Vec<&'static str> {
+    COMMANDS.iter().map(|command| command.name).collect()
+}
+
+fn bash() -> String {
+    let mut script = String::from(
+        "# bash completion for psychoquine\n\
+         _psychoquine() {\n    \
+         local cur prev command\n    \
+         cur=\"${COMP_WORDS[COMP_CWORD]}\"\n    \
+         prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n    \
+         command=generate\n",
+    );
+    script.push_str(&format!(
+        "    if [[ $COMP_CWORD -gt 1 ]]; then\n        \
+         case \"${{COMP_WORDS[1]}}\" in\n            \
+         {}) command=\"${{COMP_WORDS[1]}}\" ;;\n        \
+         esac\n    \
+         elif [[ \"$cur\" != -* ]]; then\n        \
+         COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n        \
+         return\n    \
+         fi\n\n    \
+         case \"$command\" in\n",
+        command_names().join("|"),
+        command_names().join(" ")
+    ));
+
+    for command in COMMANDS {
+        script.push_str(&format!("        {})\n", command.name));
+        script.push_str("            case \"$prev\" in\n");
+        for spec in command.all_options().filter(|spec| spec.value.is_some()) {
+            let action = match values(spec) {
+                Values::Nothing => "return".to_string(),
+                Values::Files => {
+                    "compopt -o filenames; COMPREPLY=($(compgen -f -- \"$cur\")); return"
+                        .to_string()
+                }
+                Values::Directories => {
+                    "compopt -o filenames; COMPREPLY=($(compgen -d -- \"$cur\")); return"
+                        .to_string()
+                }
+                Values::Choices(choices) => format!(
+                    "COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")); return",
+                    choices.join(" ")
+                ),
+            };
+            script.push_str(&format!(
+                "                {}) {} ;;\n",
+                spellings(spec).join("|"),
+                action
+            ));
+        }
+        script.push_str("            esac\n");
+
+        let options: Vec<String> = command.all_options().flat_map(spellings).collect();
+        let positional = match command.positional {
+            Positional::Text => String::new(),
+            Positional::Files => {
+                "compopt -o filenames; COMPREPLY=($(compgen -f -- \"$cur\"))".to_string()
+            }
+            Positional::Choices(choices) => format!(
+                "COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+                choices().join(" ")
+            ),
+        };
+        script.push_str(&format!(
+            "            if [[ \"$cur\" == -* ]]; then\n                \
+             COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n",
+            options.join(" ")
+        ));
+        if !positional.is_empty() {
+            script.push_str(&format!(
+                "            else\n                {}\n",
+                positional
+            ));
+        }
+        script.push_str("            fi\n            ;;\n");
+    }
+
+    script.push_str("    esac\n}\ncomplete -F _psychoquine psychoquine\n");
+    script
+}
+
+/// Quote text for a single-quoted zsh `_arguments` spec
+fn zsh_quote(text: &str) -> String {
+    text.replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+}
+
+fn zsh_arguments(command: &Command) -> Vec<String> {
+    let mut specs = Vec::new();
+    for spec in command.all_options() {
+        let names = match spec.short {
+            Some(short) => format!("{{-{},--{}}}", short, spec.long),
+            None => format!("--{}", spec.long),
+        };
+        let mut argument = format!("'[{}]", zsh_quote(&spec.description()));
+        if let Some(value) = spec.value {
+            let action = match values(spec) {
+                Values::Nothing => " ".to_string(),
+                Values::Files => "_files".to_string(),
+                Values::Directories => "_files -/".to_string(),
+                Values::Choices(choices) => format!("({})", choices.join(" ")),
+            };
+            argument.push_str(&format!(":{}:{}", value, action));
+        }
+        argument.push('\'');
+        specs.push(format!("{}{}", names, argument));
+    }
+
+    let word = command
+        .args
+        .trim_matches(|c| c == '[' || c == ']' || c == '.');
+    match command.positional {
+        Positional::Text if command.args.is_empty() => {}
+        Positional::Text => specs.push(format!("'*:{}: '", word)),
+        Positional::Files => specs.push(format!("'*:{}:_files'", word)),
+        Positional::Choices(choices) => {
+            specs.push(format!("'1:{}:({})'", word, choices().join(" ")))
+        }
+    }
+    specs
+}
+
+fn zsh() -> String {
+    let mut script = String::from(
+        "#compdef psychoquine\n\n_psychoquine() {\n    local -a commands\n    commands=(\n",
+    );
+    for command in COMMANDS {
+        script.push_str(&format!(
+            "        '{}:{}'\n",
+            command.name,
+            zsh_quote(command.about)
+        ));
+    }
+    script.push_str(
+        "    )\n\n    \
+         local command=generate\n    \
+         if (( CURRENT > 2 )) && (( ${commands[(I)${words[2]}:*]} )); then\n        \
+         command=$words[2]\n        \
+         shift words\n        \
+         (( CURRENT-- ))\n    \
+         elif (( CURRENT == 2 )) && [[ $words[2] != -* ]]; then\n        \
+         _describe -t commands 'psychoquine command' commands\n        \
+         return\n    \
+         fi\n\n    \
+         case $command in\n",
+    );
+    for command in COMMANDS {
+        script.push_str(&format!(
+            "        {})\n            _arguments -s",
+            command.name
+        ));
+        for spec in zsh_arguments(command) {
+            script.push_str(" \\\n                ");
+            script.push_str(&spec);
+        }
+        script.push_str("\n            ;;\n");
+    }
+    script.push_str("    esac\n}\n\n_psychoquine \"$@\"\n");
+    script
+}
+
+/// Quote text as a single-quoted fish string
+fn fish_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn fish() -> String {
+    let mut script =
+        String::from("# fish completion for psychoquine\ncomplete -c psychoquine -f\n");
+    for command in COMMANDS {
+        script.push_str(&format!(
+            "complete -c psychoquine -n __fish_use_subcommand -a {} -d {}\n",
+            command.name,
+            fish_quote(command.about)
+        ));
+    }
+
+    // Generate's options also apply when no subcommand is given
+    let others: Vec<&str> = command_names().into_iter().skip(1).collect();
+    for command in COMMANDS {
+        let condition = if command.name == commands::GENERATE.name {
+            format!("not __fish_seen_subcommand_from {}", others.join(" "))
+        } else {
+            format!("__fish_seen_subcommand_from {}", command.name)
+        };
+        script.push('\n');
+        for spec in command.all_options() {
+            let mut line = format!("complete -c psychoquine -n {}", fish_quote(&condition));
+            if let Some(short) = spec.short {
+                line.push_str(&format!(" -s {}", short));
+            }
+            line.push_str(&format!(" -l {}", spec.long));
+            if spec.value.is_some() {
+                match values(spec) {
+                    Values::Nothing => line.push_str(" -x"),
+                    Values::Files => line.push_str(" -r -F"),
+                    Values::Directories => line.push_str(" -x -a '(__fish_complete_directories)'"),
+                    Values::Choices(choices) => {
+                        line.push_str(&format!(" -x -a {}", fish_quote(&choices.join(" "))))
+                    }
+                }
+            }
+            line.push_str(&format!(" -d {}\n", fish_quote(&spec.description())));
+            script.push_str(&line);
+        }
+        match command.positional {
+            Positional::Text => {}
+            Positional::Files => script.push_str(&format!(
+                "complete -c psychoquine -n {} -F\n",
+                fish_quote(&condition)
+            )),
+            Positional::Choices(choices) => script.push_str(&format!(
+                "complete -c psychoquine -n {} -x -a {}\n",
+                fish_quote(&condition),
+                fish_quote(&choices().join(" "))
+            )),
+        }
+    }
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scripts_cover_every_option() {
+        for shell in shell_names() {
+            let script = render(Shell::from_name(shell).unwrap());
+            for command in COMMANDS {
+                assert!(script.contains(command.name), "{}: {}", shell, command.name);
+                for spec in command.all_options() {
+                    assert!(
+                        script.contains(spec.long),
+                        "{}: {} --{}",
+                        shell,
+                        command.name,
+                        spec.long
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_quoting() {
+        assert_eq!(zsh_quote("a [b]: 'c'"), "a \\[b\\]: '\\''c'\\''");
+        assert_eq!(fish_quote("it's \\"), "'it\\'s \\\\'");
+    }
+}
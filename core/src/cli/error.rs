@@ -0,0 +1,178 @@
+//! CLI errors and exit codes
+//!
+//! Every failure maps to its own exit code so scripts can tell a bad
+//! invocation from bad input or a quine that doesn't reproduce itself.
+//! The codes are documented in the user guide; don't renumber them.
+
+use std::fmt;
+use std::io;
+use std::process::ExitCode;
+
+use psychoquine_core::QuineError;
+
+/// Arguments were invalid
+pub const EXIT_USAGE: u8 = 2;
+/// A file or stream could not be read or written
+pub const EXIT_IO: u8 = 3;
+/// `QuineError::EmptyInput`
+pub const EXIT_EMPTY_INPUT: u8 = 10;
+/// `QuineError::InputTooLarge`
+pub const EXIT_INPUT_TOO_LARGE: u8 = 11;
+/// `QuineError::InvalidUtf8`
+pub const EXIT_INVALID_UTF8: u8 = 12;
+/// `QuineError::FormattingError`
+pub const EXIT_FORMATTING: u8 = 13;
+/// `QuineError::UnrecognizedQuine`
+pub const EXIT_UNRECOGNIZED_QUINE: u8 = 14;
+/// `QuineError::InterpreterError`
+pub const EXIT_INTERPRETER: u8 = 15;
+/// `QuineError::InvalidConfig`
+pub const EXIT_INVALID_CONFIG: u8 = 16;
+/// `QuineError::Cancelled`
+pub const EXIT_CANCELLED: u8 = 17;
+/// `QuineError::Timeout`
+pub const EXIT_TIMEOUT: u8 = 18;
+/// A quine ran but did not print its own source
+pub const EXIT_VERIFICATION_FAILED: u8 = 20;
+
+/// Every exit code and its meaning, for help and the man page
+pub const EXIT_CODES: &[(u8, &str)] = &[
+    (0, "success"),
+    (EXIT_USAGE, "invalid arguments"),
+    (EXIT_IO, "a file or stream could not be read or written"),
+    (EXIT_EMPTY_INPUT, "empty input"),
+    (EXIT_INPUT_TOO_LARGE, "input too large"),
+    (EXIT_INVALID_UTF8, "input is not valid UTF-8"),
+    (EXIT_FORMATTING, "formatting error"),
+    (EXIT_UNRECOGNIZED_QUINE, "not a recognized quine"),
+    (EXIT_INTERPRETER, "the interpreter could not be run"),
+    (EXIT_INVALID_CONFIG, "invalid configuration"),
+    (EXIT_CANCELLED, "generation was cancelled"),
+    (EXIT_TIMEOUT, "the interpreter did not finish in time"),
+    (
+        EXIT_VERIFICATION_FAILED,
+        "the quine did not reproduce itself",
+    ),
+];
+
+/// A failure of a CLI command
+#[derive(Debug)]
+pub enum CliError {
+    /// Invalid arguments
+    Usage(String),
+    /// Reading or writing a file or stream failed
+    Io(String),
+    /// Generating or inspecting a quine failed
+    Quine(QuineError),
+    /// A quine did not reproduce itself
+    VerificationFailed(String),
+}
+
+impl CliError {
+    /// Wrap an I/O error with what was being done, treating undecodable
+    /// text as `QuineError::InvalidUtf8`
+    pub fn io(context: impl fmt::Display, error: io::Error) -> Self {
+        if error.kind() == io::ErrorKind::InvalidData {
+            CliError::Quine(QuineError::InvalidUtf8)
+        } else {
+            CliError::Io(format!("{}: {}", context, error))
+        }
+    }
+
+    /// Stable snake_case identifier, for machine-readable output
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CliError::Usage(_) => "usage",
+            CliError::Io(_) => "io",
+            CliError::Quine(error) => error.kind(),
+            CliError::VerificationFailed(_) => "verification_failed",
+        }
+    }
+
+    /// Process exit code for this error
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            CliError::Usage(_) => EXIT_USAGE,
+            CliError::Io(_) => EXIT_IO,
+            CliError::Quine(error) => match error {
+                QuineError::EmptyInput => EXIT_EMPTY_INPUT,
+                QuineError::InputTooLarge { .. } => EXIT_INPUT_TOO_LARGE,
+                QuineError::InvalidUtf8 => EXIT_INVALID_UTF8,
+                QuineError::FormattingError(_) => EXIT_FORMATTING,
+                QuineError::UnrecognizedQuine(_) => EXIT_UNRECOGNIZED_QUINE,
+                QuineError::InterpreterError(_) => EXIT_INTERPRETER,
+                QuineError::InvalidConfig(_) => EXIT_INVALID_CONFIG,
+                QuineError::Cancelled => EXIT_CANCELLED,
+                QuineError::Timeout(_) => EXIT_TIMEOUT,
+            },
+            CliError::VerificationFailed(_) => EXIT_VERIFICATION_FAILED,
+        }
+    }
+
+    /// Print the error to stderr and return its exit code
+    pub fn report(&self) -> ExitCode {
+        eprintln!("error: {}", self);
+        if let CliError::Usage(_) = self {
+            eprintln!("Run 'psychoquine --help' for usage.");
+        }
+        ExitCode::from(self.exit_code())
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(message)
+            | CliError::Io(message)
+            | CliError::VerificationFailed(message) => write!(f, "{}", message),
+            CliError::Quine(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl From<QuineError> for CliError {
+    fn from(error: QuineError) -> Self {
+        CliError::Quine(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let errors = [
+            CliError::Usage(String::new()),
+            CliError::Io(String::new()),
+            CliError::Quine(QuineError::EmptyInput),
+            CliError::Quine(QuineError::InputTooLarge { max: 1, actual: 2 }),
+            CliError::Quine(QuineError::InvalidUtf8),
+            CliError::Quine(QuineError::FormattingError(String::new())),
+            CliError::Quine(QuineError::UnrecognizedQuine(String::new())),
+            CliError::Quine(QuineError::InterpreterError(String::new())),
+            CliError::Quine(QuineError::InvalidConfig(String::new())),
+            CliError::Quine(QuineError::Cancelled),
+            CliError::Quine(QuineError::Timeout(std::time::Duration::ZERO)),
+            CliError::VerificationFailed(String::new()),
+        ];
+        let codes: Vec<u8> = errors.iter().map(CliError::exit_code).collect();
+        for (i, code) in codes.iter().enumerate() {
+            assert!(*code > 1, "codes 0 and 1 are reserved");
+            assert!(!codes[..i].contains(code), "duplicate exit code {}", code);
+            assert!(
+                EXIT_CODES.iter().any(|(documented, _)| documented == code),
+                "exit code {} is not documented",
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_data_is_invalid_utf8() {
+        let error = CliError::io("stdin", io::Error::new(io::ErrorKind::InvalidData, "bad"));
+        assert_eq!(error.exit_code(), EXIT_INVALID_UTF8);
+        let error = CliError::io("x.txt", io::Error::new(io::ErrorKind::NotFound, "gone"));
+        assert_eq!(error.to_string(), "x.txt: gone");
+    }
+}
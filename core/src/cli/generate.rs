@@ -0,0 +1,471 @@
+//! `psychoquine generate`: turn input text into quines
+
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use psychoquine_core::{highlight, HighlightFormat, OutputFormat, QuineOutput};
+
+use super::args::{self, choice, Arg, OptSpec};
+use super::error::{self, CliError};
+use super::options::{Generation, GenerationFlags, GENERATION_OPTIONS};
+use super::output::{self, dir_outputs, output_paths};
+use super::report::{self, ErrorRecord, Format, Record};
+
+#[derive(Default)]
+struct Args {
+    help: bool,
+    version: bool,
+    generation: GenerationFlags,
+    show_stats: bool,
+    quiet: bool,
+    html: bool,
+    color: Option<String>,
+    input: Option<String>,
+    input_paths: Vec<String>,
+    output: Option<String>,
+    out_dir: Option<String>,
+    format: Option<String>,
+}
+
+pub const OPTIONS: &[OptSpec] = &[
+    OptSpec::flag(Some('h'), "help", "Show this help message"),
+    OptSpec::flag(Some('v'), "version", "Show version information"),
+    OptSpec::with_value(
+        Some('i'),
+        "input",
+        "PATH",
+        "Read input from a file (repeatable)",
+    ),
+    OptSpec::with_value(
+        None,
+        "output",
+        "PATH",
+        "Write quines to PATH instead of stdout",
+    ),
+    OptSpec::with_value(
+        None,
+        "out-dir",
+        "DIR",
+        "Write quines for each input into DIR",
+    ),
+    OptSpec::with_value(None, "format", "FMT", "Output format")
+        .choices(report::format_names)
+        .default("text"),
+    OptSpec::flag(Some('s'), "stats", "Show generation statistics"),
+    OptSpec::flag(Some('q'), "quiet", "Suppress banner and decorations"),
+    OptSpec::with_value(None, "color", "WHEN", "Highlight output")
+        .choices(color_names)
+        .default("auto"),
+    OptSpec::flag(
+        None,
+        "html",
+        "Output a highlighted standalone HTML document",
+    ),
+];
+
+fn color_names() -> Vec<&'static str> {
+    vec!["auto", "always", "never"]
+}
+
+fn parse_args(argv: &[String]) -> Result<Args, CliError> {
+    let mut args = Args::default();
+
+    for arg in args::parse(argv, &[GENERATION_OPTIONS, OPTIONS].concat())? {
+        if args.generation.accept(&arg) {
+            continue;
+        }
+        match arg {
+            Arg::Opt("help", _) => args.help = true,
+            Arg::Opt("version", _) => args.version = true,
+            Arg::Opt("stats", _) => args.show_stats = true,
+            Arg::Opt("quiet", _) => args.quiet = true,
+            Arg::Opt("html", _) => args.html = true,
+            Arg::Opt("color", value) => args.color = value,
+            Arg::Opt("input", value) => args.input_paths.extend(value),
+            Arg::Opt("output", value) => args.output = value,
+            Arg::Opt("out-dir", value) => args.out_dir = value,
+            Arg::Opt("format", value) => args.format = value,
+            Arg::Opt(other, _) => unreachable!("option --{} has no handler", other),
+            Arg::Positional(text) => {
+                if args.input.is_some() {
+                    return Err(CliError::Usage(format!(
+                        "unexpected argument '{}': input was already given \
+                         (quote input that contains spaces)",
+                        text
+                    )));
+                }
+                args.input = Some(text);
+            }
+        }
+    }
+
+    Ok(args)
+}
+
+/// Write a quine followed by exactly one newline, highlighted if requested
+fn write_quine<W: Write>(
+    handle: &mut W,
+    output: &QuineOutput,
+    which: OutputFormat,
+    color: bool,
+) -> io::Result<()> {
+    if !color {
+        return write!(handle, "{}", output.source(which));
+    }
+
+    let quine = highlight(output, HighlightFormat::Ansi, which);
+    if quine.ends_with('\n') {
+        write!(handle, "{}", quine)
+    } else {
+        writeln!(handle, "{}", quine)
+    }
+}
+
+/// Run `psychoquine generate`
+pub fn run(argv: &[String]) -> ExitCode {
+    let args = match parse_args(argv) {
+        Ok(args) => args,
+        Err(e) => return e.report(),
+    };
+
+    if args.help {
+        crate::print_help();
+        return ExitCode::SUCCESS;
+    }
+
+    if args.version {
+        crate::print_version();
+        return ExitCode::SUCCESS;
+    }
+
+    // Parse output format first, so later errors are reported in it
+    let format = match report::parse_format(args.format.as_deref()) {
+        Ok(format) => format,
+        Err(e) => return e.report(),
+    };
+    match settings(&args, format) {
+        Ok(settings) => generate(&args, format, settings),
+        Err(e) => report::fail(format, &e),
+    }
+}
+
+/// Option values checked before any input is read
+struct Settings {
+    generation: Generation,
+    color: bool,
+}
+
+fn settings(args: &Args, format: Format) -> Result<Settings, CliError> {
+    if format != Format::Text && args.html {
+        return Err(CliError::Usage(
+            "--html cannot be combined with --format json or ndjson".to_string(),
+        ));
+    }
+    if args.output.is_some() && args.out_dir.is_some() {
+        return Err(CliError::Usage(
+            "--output and --out-dir cannot be combined".to_string(),
+        ));
+    }
+
+    let generation = args.generation.resolve()?;
+
+    // Decide whether to highlight terminal output
+    let color = match args.color.as_deref() {
+        Some(when) => choice("--color", when, &color_names(), |when| match when {
+            "auto" => Some(io::stdout().is_terminal()),
+            "always" => Some(true),
+            "never" => Some(false),
+            _ => None,
+        })?,
+        None => io::stdout().is_terminal(),
+    };
+
+    Ok(Settings { generation, color })
+}
+
+/// Generate quines for every input and print or write them
+fn generate(args: &Args, format: Format, settings: Settings) -> ExitCode {
+    let Settings { generation, color } = settings;
+    let which = generation.which;
+
+    // Collect inputs from the argument, --input files or stdin
+    let sources = if args.input.is_none() && args.input_paths.is_empty() {
+        // Check if stdin has data
+        if io::stdin().is_terminal() {
+            if format != Format::Text {
+                return report::fail(format, &CliError::Usage("no input given".to_string()));
+            }
+            crate::print_help();
+            return ExitCode::from(error::EXIT_USAGE);
+        }
+        match super::read_stdin(generation.max_input_size) {
+            Ok(text) => vec![Source {
+                path: None,
+                text: Ok(text),
+            }],
+            Err(e) => return report::fail(format, &e),
+        }
+    } else {
+        read_sources(
+            args.input.clone(),
+            &args.input_paths,
+            generation.max_input_size,
+        )
+    };
+    // Structured output reports unreadable files in their own records
+    if format == Format::Text {
+        if let Some(Err(e)) = sources
+            .iter()
+            .map(|source| &source.text)
+            .find(|text| text.is_err())
+        {
+            return report::fail(format, e);
+        }
+    }
+
+    if args.output.is_some() && sources.len() > 1 {
+        return report::fail(
+            format,
+            &CliError::Usage(
+                "--output takes a single input; use --out-dir for several".to_string(),
+            ),
+        );
+    }
+
+    let to_files = args.output.is_some() || args.out_dir.is_some();
+    if let Some(dir) = &args.out_dir {
+        if let Err(e) = fs::create_dir_all(dir) {
+            return report::fail(format, &CliError::io(format!("cannot create {}", dir), e));
+        }
+    }
+    // Decorations only accompany plain text on stdout
+    let decorate = format == Format::Text && !args.quiet;
+    if decorate && !to_files && !args.html {
+        eprintln!("{}", crate::BANNER);
+    }
+
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    let mut written: Vec<PathBuf> = Vec::new();
+    let mut records: Vec<Record> = Vec::new();
+    // Exit code of the first input that failed
+    let mut failed: Option<u8> = None;
+
+    for source in &sources {
+        let label = source.path.as_deref().unwrap_or("input");
+        let text = match &source.text {
+            Ok(text) => text,
+            Err(e) => {
+                let record = Record::failure(source.path.clone(), ErrorRecord::from(e));
+                emit(&mut handle, format, record, &mut records);
+                failed = failed.or(Some(e.exit_code()));
+                continue;
+            }
+        };
+
+        let generated = generation.generate(text).map(|(output, golf)| {
+            if let (Some(golf), true) = (&golf, decorate) {
+                eprintln!(
+                    "Golf: {} bytes (default {} bytes, {} candidates)",
+                    golf.size, golf.baseline_size, golf.candidates
+                );
+            }
+            (output, golf)
+        });
+        let (result, golf_summary) = match generated {
+            Ok(generated) => generated,
+            Err(e) if format != Format::Text => {
+                // Structured output reports the failure and moves on
+                let error = CliError::Quine(e);
+                let record = Record::failure(source.path.clone(), ErrorRecord::from(&error));
+                emit(&mut handle, format, record, &mut records);
+                failed = failed.or(Some(error.exit_code()));
+                continue;
+            }
+            Err(e) if sources.len() > 1 => {
+                eprintln!("error: {}: {}", label, e);
+                return ExitCode::from(CliError::Quine(e).exit_code());
+            }
+            Err(e) => return CliError::Quine(e).report(),
+        };
+
+        let paths = match (&args.output, &args.out_dir) {
+            (Some(output), _) => Some(output_paths(
+                Path::new(output),
+                generation.language,
+                which,
+                args.html,
+            )),
+            (None, Some(dir)) => Some(dir_outputs(
+                Path::new(dir),
+                source.stem(),
+                generation.language,
+                which,
+                args.html,
+            )),
+            (None, None) => None,
+        };
+
+        let mut files = Vec::new();
+        if let Some(paths) = paths {
+            // Write each requested quine to its own file
+            for (which, path) in paths {
+                if written.contains(&path) {
+                    let message = format!(
+                        "refusing to overwrite {} written earlier in this run",
+                        path.display()
+                    );
+                    return report::fail(format, &CliError::Io(message));
+                }
+                let contents = if args.html {
+                    highlight(&result, HighlightFormat::Html, which)
+                } else {
+                    result.source(which)
+                };
+                if let Err(e) = fs::write(&path, contents) {
+                    let context = format!("cannot write {}", path.display());
+                    return report::fail(format, &CliError::io(context, e));
+                }
+                if decorate {
+                    eprintln!("Wrote {}", path.display());
+                }
+                files.push(path.display().to_string());
+                written.push(path);
+            }
+        } else if format != Format::Text {
+            // The record carries the quines
+        } else if args.html {
+            // HTML export replaces the plain text output entirely
+            write!(
+                handle,
+                "{}",
+                highlight(&result, HighlightFormat::Html, which)
+            )
+            .ok();
+        } else {
+            if sources.len() > 1 && !args.quiet {
+                eprintln!("═══ {} ═══", label);
+            }
+            if which == OutputFormat::OneLine {
+                if !args.quiet {
+                    eprintln!("═══ ONE-LINE QUINE ═══");
+                }
+                write_quine(&mut handle, &result, OutputFormat::OneLine, color).ok();
+            } else if which == OutputFormat::MultiLine {
+                if !args.quiet {
+                    eprintln!("═══ MULTI-LINE QUINE ═══");
+                }
+                write_quine(&mut handle, &result, OutputFormat::MultiLine, color).ok();
+            } else {
+                if !args.quiet {
+                    eprintln!("═══ ONE-LINE QUINE ═══");
+                }
+                write_quine(&mut handle, &result, OutputFormat::OneLine, color).ok();
+                if !args.quiet {
+                    eprintln!();
+                    eprintln!("═══ MULTI-LINE QUINE ═══");
+                }
+                write_quine(&mut handle, &result, OutputFormat::MultiLine, color).ok();
+            }
+        }
+
+        if format != Format::Text {
+            let mut record = Record::success(source.path.clone(), result);
+            record.golf = golf_summary;
+            record.files = files;
+            emit(&mut handle, format, record, &mut records);
+            continue;
+        }
+
+        // Show stats if requested
+        if args.show_stats {
+            eprintln!();
+            eprintln!("═══ STATISTICS ═══");
+            eprintln!("Input size:       {} bytes", result.stats.input_bytes);
+            eprintln!("One-line size:    {} bytes", result.stats.one_line_bytes);
+            eprintln!("Multi-line size:  {} bytes", result.stats.multi_line_bytes);
+            eprintln!("Expansion ratio:  {:.2}x", result.stats.expansion_ratio);
+        }
+    }
+
+    if format == Format::Json {
+        writeln!(handle, "{}", report::to_document(&records)).ok();
+    }
+
+    match failed {
+        Some(code) => ExitCode::from(code),
+        None => ExitCode::SUCCESS,
+    }
+}
+
+/// Print an NDJSON record now, or keep a JSON record for the final document
+fn emit<W: Write>(handle: &mut W, format: Format, record: Record, records: &mut Vec<Record>) {
+    if format == Format::Ndjson {
+        writeln!(handle, "{}", record.to_line()).ok();
+    } else {
+        records.push(record);
+    }
+}
+
+/// One input to generate quines from
+struct Source {
+    /// Path of the input file; `None` for the positional argument or stdin
+    path: Option<String>,
+    /// The input, or why its file could not be read
+    text: Result<String, CliError>,
+}
+
+impl Source {
+    /// Base name for output files
+    fn stem(&self) -> &str {
+        self.path
+            .as_deref()
+            .map(|path| output::file_stem(Path::new(path)))
+            .unwrap_or(output::DEFAULT_STEM)
+    }
+}
+
+/// Read the positional input and every `--input` file, keeping going
+/// past files that cannot be read
+fn read_sources(input: Option<String>, paths: &[String], max: usize) -> Vec<Source> {
+    let mut sources: Vec<Source> = input
+        .into_iter()
+        .map(|text| Source {
+            path: None,
+            text: Ok(text),
+        })
+        .collect();
+    for path in paths {
+        sources.push(Source {
+            path: Some(path.clone()),
+            text: super::read_file(Path::new(path), max),
+        });
+    }
+    sources
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_sources_keeps_going() {
+        let dir = std::env::temp_dir().join(format!("psychoquine-sources-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let good = dir.join("good.txt");
+        fs::write(&good, "hi").unwrap();
+        let paths = [
+            dir.join("missing.txt").display().to_string(),
+            good.display().to_string(),
+        ];
+
+        let sources = read_sources(Some("arg".to_string()), &paths, 10);
+        assert_eq!(sources.len(), 3);
+        assert_eq!(sources[0].text.as_deref().unwrap(), "arg");
+        assert!(matches!(sources[1].text, Err(CliError::Io(_))));
+        assert_eq!(sources[2].text.as_deref().unwrap(), "hi");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
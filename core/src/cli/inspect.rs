@@ -0,0 +1,155 @@
+//! `psychoquine verify`, `extract` and `explain`: inspect existing quines
+
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+use psychoquine_core::{analyze, extract as extract_payload, Language, VerifyMethod};
+
+use super::args::{self, Arg, OptSpec};
+use super::commands;
+use super::error::CliError;
+
+pub const VERIFY_OPTIONS: &[OptSpec] = &[
+    OptSpec::flag(Some('h'), "help", "Show this help message"),
+    OptSpec::flag(
+        None,
+        "static",
+        "Rebuild the output without running the interpreter",
+    ),
+    OptSpec::flag(Some('q'), "quiet", "Print nothing on success"),
+];
+
+/// Arguments shared by the inspection subcommands
+#[derive(Default)]
+struct Args {
+    help: bool,
+    quiet: bool,
+    static_only: bool,
+    file: Option<String>,
+}
+
+fn parse_args(argv: &[String], specs: &[OptSpec]) -> Result<Args, CliError> {
+    let mut args = Args::default();
+    for arg in args::parse(argv, specs)? {
+        match arg {
+            Arg::Opt("help", _) => args.help = true,
+            Arg::Opt("quiet", _) => args.quiet = true,
+            Arg::Opt("static", _) => args.static_only = true,
+            Arg::Opt(other, _) => unreachable!("option --{} has no handler", other),
+            Arg::Positional(file) => {
+                if let Some(first) = &args.file {
+                    return Err(CliError::Usage(format!(
+                        "unexpected argument '{}': file '{}' was already given",
+                        file, first
+                    )));
+                }
+                args.file = Some(file);
+            }
+        }
+    }
+    Ok(args)
+}
+
+/// Parse arguments and read the quine; `None` when only help was asked for
+fn load(name: &str, argv: &[String]) -> Result<Option<(Args, String)>, CliError> {
+    let command = commands::find(name).expect("command is described");
+    let specs: Vec<OptSpec> = command.all_options().copied().collect();
+    let args = parse_args(argv, &specs)?;
+
+    if args.help {
+        commands::print_help(name);
+        return Ok(None);
+    }
+
+    let source = match args.file.as_deref() {
+        // Quines are inspected whatever their size
+        Some("-") => super::read_stdin(usize::MAX)?,
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|e| CliError::io(format!("cannot read {}", path), e))?,
+        None => {
+            return Err(CliError::Usage(format!(
+                "missing FILE\nUsage: {}",
+                command.usage()
+            )))
+        }
+    };
+
+    Ok(Some((args, source)))
+}
+
+/// Turn a command's result into an exit code, reporting any error
+fn finish(result: Result<(), CliError>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => e.report(),
+    }
+}
+
+/// Run `psychoquine verify`
+pub fn verify(argv: &[String]) -> ExitCode {
+    finish(run_verify(argv))
+}
+
+fn run_verify(argv: &[String]) -> Result<(), CliError> {
+    let Some((args, source)) = load("verify", argv)? else {
+        return Ok(());
+    };
+
+    let report = if args.static_only {
+        psychoquine_core::verify(&source)?
+    } else {
+        psychoquine_core::verify_with_interpreter(&source, Language::JavaScript)?
+    };
+
+    let method = match report.method {
+        VerifyMethod::Static => "static",
+        VerifyMethod::Runtime => "runtime",
+    };
+    match report.first_mismatch {
+        None => {
+            if !args.quiet {
+                println!(
+                    "OK: reproduces itself ({} bytes, {} check)",
+                    report.source_bytes, method
+                );
+            }
+            Ok(())
+        }
+        Some(offset) => Err(CliError::VerificationFailed(format!(
+            "output differs from source at byte {} ({} source bytes, {} output bytes, {} check)",
+            offset, report.source_bytes, report.output_bytes, method
+        ))),
+    }
+}
+
+/// Run `psychoquine extract`
+pub fn extract(argv: &[String]) -> ExitCode {
+    finish(run_extract(argv))
+}
+
+fn run_extract(argv: &[String]) -> Result<(), CliError> {
+    let Some((_, source)) = load("extract", argv)? else {
+        return Ok(());
+    };
+
+    // The payload is written back byte for byte
+    let payload = extract_payload(&source)?;
+    io::stdout()
+        .lock()
+        .write_all(payload.as_bytes())
+        .map_err(|e| CliError::io("cannot write stdout", e))
+}
+
+/// Run `psychoquine explain`
+pub fn explain(argv: &[String]) -> ExitCode {
+    finish(run_explain(argv))
+}
+
+fn run_explain(argv: &[String]) -> Result<(), CliError> {
+    let Some((_, source)) = load("explain", argv)? else {
+        return Ok(());
+    };
+
+    println!("{}", analyze(&source)?.explain());
+    Ok(())
+}
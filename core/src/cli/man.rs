@@ -0,0 +1,143 @@
+//! `psychoquine man`: the man page, in roff
+//!
+//! Rendered from the command table like `--help`, so installing it with
+//! `psychoquine man > psychoquine.1` never documents a stale option.
+
+use std::process::ExitCode;
+
+use super::args::{self, Arg, OptSpec};
+use super::commands::{self, COMMANDS, GENERATE, HELP_OPTIONS};
+use super::error::{CliError, EXIT_CODES};
+
+/// Run `psychoquine man`
+pub fn run(argv: &[String]) -> ExitCode {
+    let parsed = match args::parse(argv, HELP_OPTIONS) {
+        Ok(parsed) => parsed,
+        Err(e) => return e.report(),
+    };
+    if let Some(Arg::Positional(arg)) = parsed.iter().find(|arg| matches!(arg, Arg::Positional(_)))
+    {
+        return CliError::Usage(format!("unexpected argument '{}'", arg)).report();
+    }
+    if !parsed.is_empty() {
+        commands::print_help("man");
+        return ExitCode::SUCCESS;
+    }
+
+    print!("{}", render());
+    ExitCode::SUCCESS
+}
+
+/// Escape text for roff: backslashes, hyphens and leading control characters
+fn escape(text: &str) -> String {
+    let text = text.replace('\\', "\\e").replace('-', "\\-");
+    if text.starts_with('.') || text.starts_with('\'') {
+        format!("\\&{}", text)
+    } else {
+        text
+    }
+}
+
+/// `.TP` paragraphs for a list of options
+fn options(specs: impl Iterator<Item = &'static OptSpec>) -> String {
+    let mut page = String::new();
+    for spec in specs {
+        let mut names: Vec<String> = spec
+            .short
+            .map(|c| format!("\\fB\\-{}\\fR", c))
+            .into_iter()
+            .collect();
+        names.push(format!("\\fB\\-\\-{}\\fR", escape(spec.long)));
+        let mut term = names.join(", ");
+        if let Some(value) = spec.value {
+            term.push_str(&format!(" \\fI{}\\fR", value));
+        }
+        page.push_str(&format!(".TP\n{}\n{}\n", term, escape(&spec.description())));
+    }
+    page
+}
+
+/// The whole man page
+pub fn render() -> String {
+    let mut page = format!(
+        ".TH PSYCHOQUINE 1 \"\" \"psychoquine {}\" \"User Commands\"\n",
+        env!("CARGO_PKG_VERSION")
+    );
+    page.push_str(".SH NAME\npsychoquine \\- universal quine generator\n");
+
+    page.push_str(".SH SYNOPSIS\n");
+    page.push_str(".B psychoquine\n[\\fIOPTIONS\\fR] [\\fIINPUT\\fR]\n.br\n");
+    page.push_str(".B psychoquine\n\\fICOMMAND\\fR [\\fIOPTIONS\\fR] [\\fIARGS\\fR]\n");
+
+    page.push_str(".SH DESCRIPTION\n");
+    page.push_str(
+        "Turns text into quines: programs that print their own source code and \
+         carry the text as an embedded payload. Input is the \\fIINPUT\\fR \
+         argument, the files given with \\fB\\-\\-input\\fR, or standard input.\n",
+    );
+
+    page.push_str(".SH COMMANDS\n");
+    for command in COMMANDS {
+        page.push_str(&format!(".TP\n\\fB{}\\fR", escape(command.name)));
+        if !command.args.is_empty() {
+            page.push_str(&format!(" \\fI{}\\fR", escape(command.args)));
+        }
+        page.push_str(&format!("\n{}\n", escape(command.about)));
+    }
+    page.push_str(
+        ".PP\nWithout a command, the arguments are those of \\fBgenerate\\fR. \
+         \\fIFILE\\fR may be \\- to read from standard input.\n",
+    );
+
+    page.push_str(".SH OPTIONS\n");
+    page.push_str(&options(GENERATE.all_options()));
+    for command in COMMANDS.iter().skip(1) {
+        page.push_str(&format!(
+            ".SS {}\n.B {}\n",
+            escape(command.name),
+            escape(&command.usage())
+        ));
+        page.push_str(&options(command.all_options()));
+    }
+
+    page.push_str(".SH EXIT STATUS\n");
+    for (code, meaning) in EXIT_CODES {
+        page.push_str(&format!(".TP\n.B {}\n{}\n", code, escape(meaning)));
+    }
+
+    page.push_str(".SH FILES\n");
+    page.push_str(
+        ".TP\n.I ./psychoquine.toml\nGeneration defaults for the current directory.\n\
+         .TP\n.I $XDG_CONFIG_HOME/psychoquine/psychoquine.toml\n\
+         Generation defaults for the user, when the current directory has none \
+         (\\fI~/.config/psychoquine/\\fR if \\fBXDG_CONFIG_HOME\\fR is unset).\n",
+    );
+    page
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("one-line"), "one\\-line");
+        assert_eq!(escape(".hidden"), "\\&.hidden");
+        assert_eq!(escape("a\\b"), "a\\eb");
+    }
+
+    #[test]
+    fn test_page_documents_every_option() {
+        let page = render();
+        assert!(page.starts_with(".TH PSYCHOQUINE 1"));
+        for command in COMMANDS {
+            assert!(page.contains(&format!("\\fB{}\\fR", escape(command.name))));
+            for spec in command.all_options() {
+                assert!(page.contains(&format!("\\fB\\-\\-{}\\fR", escape(spec.long))));
+            }
+        }
+        for (code, _) in EXIT_CODES {
+            assert!(page.contains(&format!(".B {}\n", code)));
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! Subcommands of the `psychoquine` binary
+
+pub mod api;
+pub mod args;
+pub mod batch;
+pub mod commands;
+pub mod completions;
+pub mod error;
+pub mod generate;
+pub mod inspect;
+pub mod man;
+pub mod options;
+pub mod output;
+pub mod repl;
+pub mod report;
+pub mod rpc;
+pub mod serve;
+pub mod templates;
+pub mod watch;
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use psychoquine_core::QuineError;
+
+use self::error::CliError;
+
+/// Read at most `max` bytes of text, failing as soon as the input is longer
+///
+/// On overflow `QuineError::InputTooLarge` reports the bytes read so far,
+/// so an oversized stream is never held in memory in full.
+pub fn read_limited<R: Read>(reader: R, max: usize, context: &str) -> Result<String, CliError> {
+    let mut bytes = Vec::new();
+    reader
+        .take(max.saturating_add(1) as u64)
+        .read_to_end(&mut bytes)
+        .map_err(|e| CliError::io(context, e))?;
+    if bytes.len() > max {
+        return Err(QuineError::InputTooLarge {
+            max,
+            actual: bytes.len(),
+        }
+        .into());
+    }
+    String::from_utf8(bytes).map_err(|_| QuineError::InvalidUtf8.into())
+}
+
+/// Read standard input, up to `max` bytes
+pub fn read_stdin(max: usize) -> Result<String, CliError> {
+    read_limited(io::stdin().lock(), max, "cannot read stdin")
+}
+
+/// Read a text file, up to `max` bytes
+pub fn read_file(path: &Path, max: usize) -> Result<String, CliError> {
+    let context = format!("cannot read {}", path.display());
+    let file = File::open(path).map_err(|e| CliError::io(&context, e))?;
+    read_limited(file, max, &context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_limited() {
+        assert_eq!(read_limited(&b"hello"[..], 5, "x").unwrap(), "hello");
+        assert!(matches!(
+            read_limited(io::repeat(b'a'), 4, "x"),
+            Err(CliError::Quine(QuineError::InputTooLarge {
+                max: 4,
+                actual: 5
+            }))
+        ));
+        assert!(matches!(
+            read_limited(&[0xff, 0xfe][..], 5, "x"),
+            Err(CliError::Quine(QuineError::InvalidUtf8))
+        ));
+    }
+}
@@ -0,0 +1,224 @@
+//! Generation options shared by `generate` and `batch`
+//!
+//! Flags that shape the quines themselves, layered over `psychoquine.toml`.
+
+use std::path::Path;
+
+use psychoquine_core::{
+    Config, EscapeStrategy, Language, OutputFormat, ParseOptionError, QuineError, QuineGenerator,
+    QuineOutput, StyleProfile,
+};
+
+use super::args::{Arg, OptSpec};
+use super::error::CliError;
+use super::report::GolfSummary;
+
+/// Options accepted by every command that generates quines
+pub const GENERATION_OPTIONS: &[OptSpec] = &[
+    OptSpec::flag(Some('o'), "one-line", "Output only one-line quine"),
+    OptSpec::flag(Some('m'), "multi-line", "Output only multi-line quine"),
+    OptSpec::flag(Some('b'), "both", "Output both formats (default)"),
+    OptSpec::with_value(Some('e'), "escape", "TYPE", "Escape strategy").choices(escape_names),
+    OptSpec::with_value(None, "style", "NAME", "Multi-line style").choices(style_names),
+    OptSpec::with_value(
+        None,
+        "seed",
+        "N",
+        "Pick an equivalent template variant from seed N",
+    ),
+    OptSpec::flag(Some('g'), "golf", "Search for the shortest one-line quine"),
+    OptSpec::with_value(
+        None,
+        "max-size",
+        "SIZE",
+        "Largest input accepted, e.g. 64K or 10M",
+    )
+    .default("10M"),
+    OptSpec::with_value(
+        None,
+        "config",
+        "PATH",
+        "Read defaults from PATH instead of psychoquine.toml",
+    ),
+    OptSpec::flag(None, "no-config", "Ignore configuration files"),
+];
+
+fn escape_names() -> Vec<&'static str> {
+    EscapeStrategy::ALL.iter().map(|s| s.name()).collect()
+}
+
+fn style_names() -> Vec<&'static str> {
+    StyleProfile::PRESETS.to_vec()
+}
+
+/// Generation flags as given on the command line
+#[derive(Default)]
+pub struct GenerationFlags {
+    one_line: bool,
+    multi_line: bool,
+    both: bool,
+    golf: bool,
+    escape: Option<String>,
+    style: Option<String>,
+    seed: Option<String>,
+    max_size: Option<String>,
+    config: Option<String>,
+    no_config: bool,
+}
+
+impl GenerationFlags {
+    /// Record `arg` if it is a generation option; returns whether it was
+    pub fn accept(&mut self, arg: &Arg) -> bool {
+        match arg {
+            Arg::Opt("one-line", _) => self.one_line = true,
+            Arg::Opt("multi-line", _) => self.multi_line = true,
+            Arg::Opt("both", _) => {
+                self.one_line = false;
+                self.multi_line = false;
+                self.both = true;
+            }
+            Arg::Opt("golf", _) => self.golf = true,
+            Arg::Opt("escape", value) => self.escape = value.clone(),
+            Arg::Opt("style", value) => self.style = value.clone(),
+            Arg::Opt("seed", value) => self.seed = value.clone(),
+            Arg::Opt("max-size", value) => self.max_size = value.clone(),
+            Arg::Opt("config", value) => self.config = value.clone(),
+            Arg::Opt("no-config", _) => self.no_config = true,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Validate the flags and apply them over the configuration file
+    ///
+    /// Flags go through the same `GenerateRequest` as the configuration
+    /// file and the desktop app, so they accept the same names.
+    pub fn resolve(&self) -> Result<Generation, CliError> {
+        // Configuration file defaults, overridden by flags below
+        let config = if self.no_config {
+            Config::default()
+        } else if let Some(path) = &self.config {
+            Config::load(Path::new(path))?
+        } else {
+            Config::discover()?
+                .map(|(_, config)| config)
+                .unwrap_or_default()
+        };
+        let mut request = config.request();
+        if self.escape.is_some() {
+            request.escape_strategy = self.escape.clone();
+        }
+        if self.style.is_some() {
+            request.style = self.style.clone();
+        }
+        if let Some(seed) = self.seed.as_deref() {
+            request.seed = Some(seed.parse::<u64>().map_err(|_| {
+                CliError::Usage(format!(
+                    "invalid value '{}' for --seed (expected a non-negative integer)",
+                    seed
+                ))
+            })?);
+        }
+        let options = request.format_options().map_err(flag_error)?;
+
+        let which = if self.one_line && !self.multi_line {
+            OutputFormat::OneLine
+        } else if self.multi_line && !self.one_line {
+            OutputFormat::MultiLine
+        } else if self.one_line || self.multi_line || self.both {
+            OutputFormat::Both
+        } else {
+            options.output_format
+        };
+
+        let max_input_size = match self.max_size.as_deref() {
+            Some(size) => parse_size(size).ok_or_else(|| {
+                CliError::Usage(format!(
+                    "invalid value '{}' for --max-size (expected bytes, optionally \
+                     with a K, M or G suffix)",
+                    size
+                ))
+            })?,
+            None => config
+                .max_input_size
+                .unwrap_or(QuineGenerator::DEFAULT_MAX_INPUT_SIZE),
+        };
+
+        let language = options.language;
+        let generator = QuineGenerator::with_options(options).with_max_input_size(max_input_size);
+
+        Ok(Generation {
+            generator,
+            language,
+            which,
+            golf: self.golf,
+            max_input_size,
+        })
+    }
+}
+
+/// Resolved generation settings
+pub struct Generation {
+    pub generator: QuineGenerator,
+    pub language: Language,
+    /// Which quines to print or write
+    pub which: OutputFormat,
+    pub golf: bool,
+    /// Largest input accepted, in bytes
+    pub max_input_size: usize,
+}
+
+impl Generation {
+    /// Generate the quines for one input, golfing them if requested
+    pub fn generate(&self, input: &str) -> Result<(QuineOutput, Option<GolfSummary>), QuineError> {
+        if self.golf {
+            let golf = self.generator.golf(input)?;
+            let summary = GolfSummary::from(&golf);
+            Ok((golf.output, Some(summary)))
+        } else {
+            Ok((self.generator.generate(input)?, None))
+        }
+    }
+}
+
+/// Report a bad flag value under the flag's name rather than the request field's
+fn flag_error(mut error: ParseOptionError) -> CliError {
+    error.option = match error.option {
+        "escape_strategy" => "--escape",
+        "style" => "--style",
+        other => other,
+    };
+    CliError::Usage(error.to_string())
+}
+
+/// Parse a byte count such as `65536`, `64K` or `10M` (binary multiples)
+fn parse_size(size: &str) -> Option<usize> {
+    let (digits, unit) = match size.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        Some((index, _)) => size.split_at(index),
+        None => (size, ""),
+    };
+    let shift = match unit.to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 10,
+        "M" | "MB" | "MIB" => 20,
+        "G" | "GB" | "GIB" => 30,
+        _ => return None,
+    };
+    digits.parse::<usize>().ok()?.checked_mul(1 << shift)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("65536"), Some(65536));
+        assert_eq!(parse_size("64K"), Some(64 * 1024));
+        assert_eq!(parse_size("10mb"), Some(10 * 1024 * 1024));
+        assert_eq!(parse_size("1G"), Some(1 << 30));
+        for invalid in ["", "K", "-1", "1.5M", "10 M", "3T"] {
+            assert_eq!(parse_size(invalid), None, "{}", invalid);
+        }
+    }
+}
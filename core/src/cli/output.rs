@@ -0,0 +1,148 @@
+//! Naming of quine files written by the CLI
+
+use std::path::{Path, PathBuf};
+
+use psychoquine_core::{Language, OutputFormat};
+
+/// File stem used for inputs that don't come from a file
+pub const DEFAULT_STEM: &str = "quine";
+
+/// Base name for the outputs of an input file
+pub fn file_stem(path: &Path) -> &str {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(DEFAULT_STEM)
+}
+
+/// Files the requested quines are written to, given the output base path
+///
+/// A base without an extension gets the language's. When both formats are
+/// written, the one-line quine goes to `<stem>.min.<ext>` next to the
+/// multi-line `<stem>.<ext>`.
+pub fn output_paths(
+    base: &Path,
+    language: Language,
+    which: OutputFormat,
+    html: bool,
+) -> Vec<(OutputFormat, PathBuf)> {
+    let extension = if html { "html" } else { language.extension() };
+    if which != OutputFormat::Both || html {
+        let single = if base.extension().is_some() {
+            base.to_path_buf()
+        } else {
+            base.with_extension(extension)
+        };
+        return vec![(which, single)];
+    }
+
+    let dir = base.parent().unwrap_or(Path::new(""));
+    named_outputs(dir, file_stem(base), extension, which)
+}
+
+/// Files the quines of the input named `stem` are written to in `dir`
+///
+/// The stem is kept whole, dots included, and always gets the language's
+/// extension (or `html`); only an explicit `--output` path chooses its own.
+pub fn dir_outputs(
+    dir: &Path,
+    stem: &str,
+    language: Language,
+    which: OutputFormat,
+    html: bool,
+) -> Vec<(OutputFormat, PathBuf)> {
+    if html {
+        return vec![(which, dir.join(format!("{}.html", stem)))];
+    }
+    named_outputs(dir, stem, language.extension(), which)
+}
+
+/// `<stem>.<ext>` in `dir`, plus `<stem>.min.<ext>` when both formats are
+/// written
+pub fn named_outputs(
+    dir: &Path,
+    stem: &str,
+    extension: &str,
+    which: OutputFormat,
+) -> Vec<(OutputFormat, PathBuf)> {
+    let plain = dir.join(format!("{}.{}", stem, extension));
+    if which != OutputFormat::Both {
+        return vec![(which, plain)];
+    }
+    vec![
+        (
+            OutputFormat::OneLine,
+            dir.join(format!("{}.min.{}", stem, extension)),
+        ),
+        (OutputFormat::MultiLine, plain),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_paths() {
+        let js = Language::JavaScript;
+        assert_eq!(
+            output_paths(Path::new("out/hello"), js, OutputFormat::OneLine, false),
+            vec![(OutputFormat::OneLine, PathBuf::from("out/hello.js"))]
+        );
+        assert_eq!(
+            output_paths(Path::new("q.txt"), js, OutputFormat::MultiLine, false),
+            vec![(OutputFormat::MultiLine, PathBuf::from("q.txt"))]
+        );
+        assert_eq!(
+            output_paths(Path::new("out/hello.js"), js, OutputFormat::Both, false),
+            vec![
+                (OutputFormat::OneLine, PathBuf::from("out/hello.min.js")),
+                (OutputFormat::MultiLine, PathBuf::from("out/hello.js")),
+            ]
+        );
+        assert_eq!(
+            output_paths(Path::new("page"), js, OutputFormat::Both, true),
+            vec![(OutputFormat::Both, PathBuf::from("page.html"))]
+        );
+    }
+
+    #[test]
+    fn test_dir_outputs_keep_dotted_stems() {
+        let js = Language::JavaScript;
+        let dir = Path::new("od/out");
+        for input in ["od/v1.2.txt", "od/plain.txt"] {
+            let stem = file_stem(Path::new(input));
+            assert_eq!(
+                dir_outputs(dir, stem, js, OutputFormat::OneLine, false),
+                vec![(OutputFormat::OneLine, dir.join(format!("{}.js", stem)))]
+            );
+        }
+        assert_eq!(
+            dir_outputs(dir, "v1.2", js, OutputFormat::Both, false),
+            vec![
+                (OutputFormat::OneLine, PathBuf::from("od/out/v1.2.min.js")),
+                (OutputFormat::MultiLine, PathBuf::from("od/out/v1.2.js")),
+            ]
+        );
+        assert_eq!(
+            dir_outputs(dir, "v1.2", js, OutputFormat::Both, true),
+            vec![(OutputFormat::Both, PathBuf::from("od/out/v1.2.html"))]
+        );
+    }
+
+    #[test]
+    fn test_named_outputs() {
+        assert_eq!(
+            named_outputs(Path::new("docs"), "intro.quine", "js", OutputFormat::Both),
+            vec![
+                (
+                    OutputFormat::OneLine,
+                    PathBuf::from("docs/intro.quine.min.js")
+                ),
+                (
+                    OutputFormat::MultiLine,
+                    PathBuf::from("docs/intro.quine.js")
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,384 @@
+//! `psychoquine repl`: turn lines into quines interactively
+//!
+//! Every line is generated as it is entered. Lines starting with `:` are
+//! commands that change settings or inspect the last quine; start a line
+//! with `::` to generate text that begins with a colon.
+
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::process::ExitCode;
+
+use psychoquine_core::{
+    highlight, verify_with_interpreter, EscapeStrategy, FormatOptions, HighlightFormat, Language,
+    OutputFormat, QuineGenerator, QuineOutput,
+};
+
+use super::args::{self, choice, Arg};
+use super::commands::{self, HELP_OPTIONS};
+use super::error::CliError;
+use super::options::{GenerationFlags, GENERATION_OPTIONS};
+
+/// Line that ends a `:paste` block
+const PASTE_END: &str = ".";
+
+const COMMANDS: &[(&str, &str)] = &[
+    (
+        ":paste",
+        "Generate a multi-line block; end it with a line containing only '.'",
+    ),
+    (":language [NAME]", "Show or set the target language"),
+    (":escape [NAME]", "Show or set the escape strategy"),
+    (
+        ":format [NAME]",
+        "Show or set the output format: one-line, multi-line, both",
+    ),
+    (":stats", "Show statistics of the last quine"),
+    (
+        ":verify",
+        "Run the last quine and check that it prints itself",
+    ),
+    (
+        ":compare",
+        "Compare one-line sizes of the last input across escape strategies",
+    ),
+    (":settings", "Show the current settings"),
+    (":help", "Show this list"),
+    (":quit", "Leave the REPL (or press Ctrl-D)"),
+];
+
+/// Run `psychoquine repl`
+pub fn run(argv: &[String]) -> ExitCode {
+    match repl(argv) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => e.report(),
+    }
+}
+
+fn repl(argv: &[String]) -> Result<(), CliError> {
+    let mut flags = GenerationFlags::default();
+    for arg in args::parse(argv, &[GENERATION_OPTIONS, HELP_OPTIONS].concat())? {
+        if flags.accept(&arg) {
+            continue;
+        }
+        match arg {
+            Arg::Opt("help", _) => {
+                commands::print_help("repl");
+                return Ok(());
+            }
+            Arg::Opt(other, _) => unreachable!("option --{} has no handler", other),
+            Arg::Positional(arg) => {
+                return Err(CliError::Usage(format!("unexpected argument '{}'", arg)))
+            }
+        }
+    }
+
+    let generation = flags.resolve()?;
+    let mut session = Session {
+        options: generation.generator.options().clone(),
+        max_input_size: generation.max_input_size,
+        which: generation.which,
+        golf: generation.golf,
+        last: None,
+        color: io::stdout().is_terminal(),
+    };
+
+    let interactive = io::stdin().is_terminal();
+    if interactive {
+        eprintln!("PsychoQuine REPL: type text to turn it into a quine, :help for commands.");
+    }
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        if interactive {
+            eprint!("pq> ");
+            io::stderr().flush().ok();
+        }
+        let Some(line) = lines.next() else {
+            break;
+        };
+        let line = line.map_err(|e| CliError::io("cannot read stdin", e))?;
+
+        let result = match parse_line(&line) {
+            Line::Empty => Ok(()),
+            Line::Text(text) => session.generate(text),
+            Line::Command(":quit" | ":q" | ":exit", _) => break,
+            Line::Command(":paste", _) => {
+                if interactive {
+                    eprintln!(
+                        "(paste text; end with a line containing only '{}')",
+                        PASTE_END
+                    );
+                }
+                let mut block = Vec::new();
+                for line in lines.by_ref() {
+                    let line = line.map_err(|e| CliError::io("cannot read stdin", e))?;
+                    if line == PASTE_END {
+                        break;
+                    }
+                    block.push(line);
+                }
+                session.generate(&block.join("\n"))
+            }
+            Line::Command(command, argument) => session.command(command, argument),
+        };
+        if let Err(e) = result {
+            eprintln!("error: {}", e);
+        }
+        io::stdout().flush().ok();
+    }
+
+    Ok(())
+}
+
+/// What a line typed at the prompt means
+#[derive(Debug, PartialEq)]
+enum Line<'a> {
+    Empty,
+    /// Text to generate a quine from
+    Text(&'a str),
+    /// A command and its argument, if any
+    Command(&'a str, Option<&'a str>),
+}
+
+fn parse_line(line: &str) -> Line<'_> {
+    if line.is_empty() {
+        Line::Empty
+    } else if line.starts_with("::") {
+        Line::Text(&line[1..])
+    } else if line.starts_with(':') {
+        let line = line.trim();
+        match line.split_once(char::is_whitespace) {
+            Some((command, argument)) => Line::Command(command, Some(argument.trim())),
+            None => Line::Command(line, None),
+        }
+    } else {
+        Line::Text(line)
+    }
+}
+
+/// Settings and the last result of a REPL session
+struct Session {
+    options: FormatOptions,
+    max_input_size: usize,
+    which: OutputFormat,
+    /// Whether lines are golfed rather than generated with the template
+    golf: bool,
+    last: Option<QuineOutput>,
+    color: bool,
+}
+
+impl Session {
+    fn generator(&self) -> QuineGenerator {
+        QuineGenerator::with_options(self.options.clone()).with_max_input_size(self.max_input_size)
+    }
+
+    fn generate(&mut self, text: &str) -> Result<(), CliError> {
+        let output = if self.golf {
+            let golf = self.generator().golf(text)?;
+            println!(
+                "Golf: {} bytes (default {} bytes, {} candidates)",
+                golf.size, golf.baseline_size, golf.candidates
+            );
+            golf.output
+        } else {
+            self.generator().generate(text)?
+        };
+        for which in formats(self.which) {
+            if self.which == OutputFormat::Both {
+                let size = match which {
+                    OutputFormat::OneLine => output.stats.one_line_bytes,
+                    _ => output.stats.multi_line_bytes,
+                };
+                println!("{} ({} bytes):", which.name(), size);
+            }
+            if self.color {
+                let quine = highlight(&output, HighlightFormat::Ansi, which);
+                println!("{}", quine.trim_end_matches('\n'));
+            } else {
+                print!("{}", output.source(which));
+            }
+        }
+        self.last = Some(output);
+        Ok(())
+    }
+
+    fn command(&mut self, command: &str, argument: Option<&str>) -> Result<(), CliError> {
+        match command {
+            ":help" => {
+                for (usage, description) in COMMANDS {
+                    println!("  {:<18}{}", usage, description);
+                }
+                println!("  {:<18}Generate TEXT that starts with ':'", "::TEXT");
+            }
+            ":language" => {
+                let names: Vec<&str> = Language::ALL.iter().map(|l| l.name()).collect();
+                match argument {
+                    Some(name) => {
+                        self.options.language =
+                            choice(":language", name, &names, Language::from_name)?
+                    }
+                    None => println!(
+                        "language: {} ({})",
+                        self.options.language.name(),
+                        names.join(", ")
+                    ),
+                }
+            }
+            ":escape" => {
+                let names: Vec<&str> = EscapeStrategy::ALL.iter().map(|s| s.name()).collect();
+                match argument {
+                    Some(name) => {
+                        self.options.escape_strategy =
+                            choice(":escape", name, &names, EscapeStrategy::from_name)?
+                    }
+                    None => println!(
+                        "escape: {} ({})",
+                        self.options.escape_strategy.name(),
+                        names.join(", ")
+                    ),
+                }
+            }
+            ":format" => {
+                let names: Vec<&str> = OutputFormat::ALL.iter().map(|f| f.name()).collect();
+                match argument {
+                    Some(name) => {
+                        self.which = choice(":format", name, &names, OutputFormat::from_name)?
+                    }
+                    None => println!("format: {} ({})", self.which.name(), names.join(", ")),
+                }
+            }
+            ":stats" => {
+                let stats = &self.last()?.stats;
+                println!("Input size:       {} bytes", stats.input_bytes);
+                println!("One-line size:    {} bytes", stats.one_line_bytes);
+                println!("Multi-line size:  {} bytes", stats.multi_line_bytes);
+                println!("Expansion ratio:  {:.2}x", stats.expansion_ratio);
+            }
+            ":verify" => {
+                let output = self.last()?;
+                for which in formats(self.which) {
+                    let report =
+                        verify_with_interpreter(&output.source(which), self.options.language)?;
+                    match report.first_mismatch {
+                        None => println!("{}: reproduces itself", which.name()),
+                        Some(offset) => println!(
+                            "{}: output differs from source at byte {}",
+                            which.name(),
+                            offset
+                        ),
+                    }
+                }
+            }
+            ":compare" => {
+                let input = self.last()?.original.clone();
+                for strategy in EscapeStrategy::ALL {
+                    let mut options = self.options.clone();
+                    options.escape_strategy = *strategy;
+                    let generator = QuineGenerator::with_options(options)
+                        .with_max_input_size(self.max_input_size);
+                    match generator.generate(&input) {
+                        Ok(output) => println!(
+                            "  {:<10}{:>8} bytes  {:.2}x",
+                            strategy.name(),
+                            output.stats.one_line_bytes,
+                            output.stats.expansion_ratio
+                        ),
+                        Err(e) => println!("  {:<10}{}", strategy.name(), e),
+                    }
+                }
+            }
+            ":settings" => {
+                println!("language: {}", self.options.language.name());
+                println!("escape:   {}", self.options.escape_strategy.name());
+                println!("format:   {}", self.which.name());
+                println!("golf:     {}", if self.golf { "on" } else { "off" });
+            }
+            unknown => {
+                let known = COMMANDS
+                    .iter()
+                    .map(|(usage, _)| usage.split(' ').next().unwrap_or(usage));
+                return Err(CliError::Usage(match args::closest(unknown, known) {
+                    Some(command) => {
+                        format!(
+                            "unknown command '{}' (did you mean '{}'?)",
+                            unknown, command
+                        )
+                    }
+                    None => format!("unknown command '{}' (type :help for commands)", unknown),
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    fn last(&self) -> Result<&QuineOutput, CliError> {
+        self.last
+            .as_ref()
+            .ok_or_else(|| CliError::Usage("no quine yet; type some text first".to_string()))
+    }
+}
+
+/// The single formats making up `which`
+fn formats(which: OutputFormat) -> Vec<OutputFormat> {
+    match which {
+        OutputFormat::Both => vec![OutputFormat::OneLine, OutputFormat::MultiLine],
+        single => vec![single],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(parse_line(""), Line::Empty);
+        assert_eq!(parse_line("hello world"), Line::Text("hello world"));
+        assert_eq!(parse_line("::not a command"), Line::Text(":not a command"));
+        assert_eq!(parse_line(":stats"), Line::Command(":stats", None));
+        assert_eq!(
+            parse_line(":escape  unicode "),
+            Line::Command(":escape", Some("unicode"))
+        );
+    }
+
+    #[test]
+    fn test_commands_change_settings() {
+        let mut session = Session {
+            options: FormatOptions::default(),
+            max_input_size: QuineGenerator::DEFAULT_MAX_INPUT_SIZE,
+            which: OutputFormat::Both,
+            golf: false,
+            last: None,
+            color: false,
+        };
+        session.command(":escape", Some("hex")).unwrap();
+        session.command(":format", Some("one-line")).unwrap();
+        assert_eq!(session.options.escape_strategy, EscapeStrategy::Hexadecimal);
+        assert_eq!(session.which, OutputFormat::OneLine);
+
+        assert!(session.command(":stats", None).is_err());
+        assert!(session.command(":escape", Some("rot13")).is_err());
+        let unknown = session.command(":formt", None).unwrap_err().to_string();
+        assert!(unknown.contains("did you mean ':format'"), "{}", unknown);
+    }
+
+    #[test]
+    fn test_golf_flag_is_kept() {
+        let mut session = Session {
+            options: FormatOptions::default(),
+            max_input_size: QuineGenerator::DEFAULT_MAX_INPUT_SIZE,
+            which: OutputFormat::OneLine,
+            golf: true,
+            last: None,
+            color: false,
+        };
+        session.generate("hello").unwrap();
+        let golfed = session.generator().golf("hello").unwrap().output;
+        assert_eq!(session.last().unwrap().one_line, golfed.one_line);
+        assert_ne!(
+            golfed.one_line,
+            session.generator().generate("hello").unwrap().one_line
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! Machine-readable results for `--format json` and `--format ndjson`
+//!
+//! Field names are part of the CLI's interface. Renaming or removing a
+//! field requires bumping `SCHEMA_VERSION`; adding one does not.
+
+use std::process::ExitCode;
+
+use serde::Serialize;
+
+use psychoquine_core::{GolfResult, QuineOutput};
+
+use super::args::choice;
+use super::error::CliError;
+
+/// Version of the record layout below
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// How results are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Quines on stdout, decorations on stderr
+    Text,
+    /// One JSON document (an array when there are several inputs)
+    Json,
+    /// One compact JSON record per line
+    Ndjson,
+}
+
+impl Format {
+    /// Look up a format by its `--format` name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            "ndjson" => Some(Format::Ndjson),
+            _ => None,
+        }
+    }
+}
+
+/// Names accepted by `--format`
+pub fn format_names() -> Vec<&'static str> {
+    vec!["text", "json", "ndjson"]
+}
+
+/// Parse the `--format` option, defaulting to text
+pub fn parse_format(name: Option<&str>) -> Result<Format, CliError> {
+    match name {
+        Some(name) => choice("--format", name, &format_names(), Format::from_name),
+        None => Ok(Format::Text),
+    }
+}
+
+/// Result of a golf search, without the output it produced
+#[derive(Debug, Serialize)]
+pub struct GolfSummary {
+    pub size: usize,
+    pub baseline_size: usize,
+    pub candidates: usize,
+}
+
+impl From<&GolfResult> for GolfSummary {
+    fn from(golf: &GolfResult) -> Self {
+        Self {
+            size: golf.size,
+            baseline_size: golf.baseline_size,
+            candidates: golf.candidates,
+        }
+    }
+}
+
+/// A failure, identified by a stable kind
+#[derive(Debug, Serialize)]
+pub struct ErrorRecord {
+    pub kind: String,
+    pub message: String,
+}
+
+impl From<&CliError> for ErrorRecord {
+    fn from(error: &CliError) -> Self {
+        Self {
+            kind: error.kind().to_string(),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Outcome of generating quines for one input
+#[derive(Debug, Serialize)]
+pub struct Record {
+    pub schema_version: u32,
+    /// Input file path; `null` for the positional argument or stdin
+    pub input: Option<String>,
+    pub output: Option<QuineOutput>,
+    pub golf: Option<GolfSummary>,
+    /// Files the quines were written to
+    pub files: Vec<String>,
+    pub error: Option<ErrorRecord>,
+}
+
+impl Record {
+    pub fn success(input: Option<String>, output: QuineOutput) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            input,
+            output: Some(output),
+            golf: None,
+            files: Vec::new(),
+            error: None,
+        }
+    }
+
+    pub fn failure(input: Option<String>, error: ErrorRecord) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            input,
+            output: None,
+            golf: None,
+            files: Vec::new(),
+            error: Some(error),
+        }
+    }
+
+    /// Serialize compactly, for NDJSON
+    pub fn to_line(&self) -> String {
+        serde_json::to_string(self).expect("records serialize")
+    }
+}
+
+/// Serialize records as one pretty JSON document
+pub fn to_document(records: &[Record]) -> String {
+    match records {
+        [record] => serde_json::to_string_pretty(record),
+        records => serde_json::to_string_pretty(records),
+    }
+    .expect("records serialize")
+}
+
+/// Report an error that stops the run, as text or as a structured record
+pub fn fail(format: Format, error: &CliError) -> ExitCode {
+    let record = Record::failure(None, ErrorRecord::from(error));
+    match format {
+        Format::Text => return error.report(),
+        Format::Json => println!("{}", to_document(&[record])),
+        Format::Ndjson => println!("{}", record.to_line()),
+    }
+    ExitCode::from(error.exit_code())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use psychoquine_core::QuineError;
+
+    #[test]
+    fn test_record_fields_are_stable() {
+        let output = psychoquine_core::generate("hi").unwrap();
+        let value: serde_json::Value =
+            serde_json::from_str(&Record::success(None, output).to_line()).unwrap();
+        let keys: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                "error",
+                "files",
+                "golf",
+                "input",
+                "output",
+                "schema_version"
+            ]
+        );
+        assert_eq!(value["schema_version"], SCHEMA_VERSION);
+        assert_eq!(value["output"]["original"], "hi");
+        assert!(value["error"].is_null());
+    }
+
+    #[test]
+    fn test_error_record() {
+        let record = Record::failure(
+            Some("empty.txt".to_string()),
+            ErrorRecord::from(&CliError::Quine(QuineError::EmptyInput)),
+        );
+        let value: serde_json::Value = serde_json::from_str(&record.to_line()).unwrap();
+        assert_eq!(value["error"]["kind"], "empty_input");
+        assert_eq!(value["error"]["message"], "Input is empty");
+        assert_eq!(value["input"], "empty.txt");
+        assert!(value["output"].is_null());
+    }
+
+    #[test]
+    fn test_document_shape() {
+        let error = || {
+            let usage = CliError::Usage("bad".to_string());
+            Record::failure(None, ErrorRecord::from(&usage))
+        };
+        assert!(to_document(&[error()]).starts_with('{'));
+        assert!(to_document(&[error(), error()]).starts_with('['));
+    }
+}
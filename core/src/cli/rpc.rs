@@ -0,0 +1,405 @@
+//! `psychoquine rpc`: JSON-RPC 2.0 over stdin and stdout
+//!
+//! Each line of stdin is a request or a batch, and each reply is written
+//! as one line of stdout, so editors and scripts can keep one process
+//! running instead of starting the CLI per quine. Methods take the
+//! parameters the desktop app's commands take:
+//!
+//! | Method      | Params                              | Result        |
+//! |-------------|-------------------------------------|---------------|
+//! | `generate`  | `{input, options}`                  | `QuineOutput` |
+//! | `verify`    | `{source, language, method}`        | `VerifyReport`|
+//! | `extract`   | `{source}`                          | payload text  |
+//! | `templates` | none                                | `Templates`   |
+//!
+//! Errors carry `{"kind"}` in `data`, with the names the other front ends
+//! use.
+
+use std::io::{self, BufRead, Read, Write};
+use std::process::ExitCode;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use psychoquine_core::{Config, Templates};
+
+use super::api::{CallError, Engine};
+use super::args::{self, Arg};
+use super::commands::{self, HELP_OPTIONS};
+use super::error::CliError;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Generation, verification or extraction failed
+const CALL_FAILED: i64 = -32000;
+
+/// A JSON-RPC request; `id` is read separately, since a missing id and
+/// `null` mean different things
+#[derive(Debug, Deserialize)]
+struct Request {
+    jsonrpc: String,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+    id: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+    data: ErrorData,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorData {
+    kind: &'static str,
+}
+
+impl RpcError {
+    fn new(code: i64, kind: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: ErrorData { kind },
+        }
+    }
+}
+
+impl From<CallError> for RpcError {
+    fn from(error: CallError) -> Self {
+        let code = match error {
+            CallError::InvalidOption(_) => INVALID_PARAMS,
+            CallError::ExecDisabled | CallError::Quine(_) => CALL_FAILED,
+        };
+        RpcError::new(code, error.kind(), error.to_string())
+    }
+}
+
+/// What is written for one line of input
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum Reply {
+    One(Response),
+    Batch(Vec<Response>),
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Response {
+    let (result, error) = match result {
+        Ok(result) => (Some(result), None),
+        Err(error) => (None, Some(error)),
+    };
+    Response {
+        jsonrpc: "2.0",
+        result,
+        error,
+        id,
+    }
+}
+
+/// Run `psychoquine rpc`
+pub fn run(argv: &[String]) -> ExitCode {
+    match rpc(argv) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => e.report(),
+    }
+}
+
+fn rpc(argv: &[String]) -> Result<(), CliError> {
+    let parsed = args::parse(argv, HELP_OPTIONS)?;
+    if let Some(Arg::Positional(arg)) = parsed.iter().find(|arg| matches!(arg, Arg::Positional(_)))
+    {
+        return Err(CliError::Usage(format!("unexpected argument '{}'", arg)));
+    }
+    if !parsed.is_empty() {
+        commands::print_help("rpc");
+        return Ok(());
+    }
+
+    // Whoever can write to our stdin can already run programs
+    let engine = Engine::new(
+        Config::discover()?
+            .map(|(_, config)| config)
+            .unwrap_or_default(),
+    )?
+    .with_exec(true);
+    answer_lines(&engine, io::stdin().lock(), &mut io::stdout().lock())
+}
+
+/// Answer each line of `input` on `output` until end of input
+fn answer_lines(
+    engine: &Engine,
+    mut input: impl BufRead,
+    output: &mut impl Write,
+) -> Result<(), CliError> {
+    let max = engine.max_request_size();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let read = Read::take(&mut input, max.saturating_add(1) as u64)
+            .read_until(b'\n', &mut line)
+            .map_err(|e| CliError::io("cannot read stdin", e))?;
+        if read == 0 {
+            return Ok(());
+        }
+
+        let reply = if line.len() > max && !line.ends_with(b"\n") {
+            // Drop the rest of the line rather than reading it as a request
+            input
+                .skip_until(b'\n')
+                .map_err(|e| CliError::io("cannot read stdin", e))?;
+            Some(Reply::One(response(
+                Value::Null,
+                Err(RpcError::new(
+                    INVALID_REQUEST,
+                    "request_too_large",
+                    format!("requests are limited to {} bytes", max),
+                )),
+            )))
+        } else if line.trim_ascii().is_empty() {
+            None
+        } else {
+            answer(engine, &line)
+        };
+
+        if let Some(reply) = reply {
+            let reply = serde_json::to_string(&reply).expect("replies serialize");
+            writeln!(output, "{}", reply)
+                .and_then(|()| output.flush())
+                .map_err(|e| CliError::io("cannot write stdout", e))?;
+        }
+    }
+}
+
+/// Answer one line: a request or a batch, `None` if it was only
+/// notifications
+fn answer(engine: &Engine, line: &[u8]) -> Option<Reply> {
+    match serde_json::from_slice(line) {
+        Ok(Value::Array(calls)) if !calls.is_empty() => {
+            let replies: Vec<Response> = calls
+                .into_iter()
+                .filter_map(|call| answer_call(engine, call))
+                .collect();
+            (!replies.is_empty()).then_some(Reply::Batch(replies))
+        }
+        Ok(call) => answer_call(engine, call).map(Reply::One),
+        Err(e) => Some(Reply::One(response(
+            Value::Null,
+            Err(RpcError::new(PARSE_ERROR, "parse_error", e.to_string())),
+        ))),
+    }
+}
+
+/// Answer one call, or nothing if it is a notification
+fn answer_call(engine: &Engine, call: Value) -> Option<Response> {
+    let id = call.get("id").cloned();
+    let request = match serde_json::from_value::<Request>(call) {
+        Ok(request) if request.jsonrpc == "2.0" => request,
+        Ok(_) => {
+            let error = RpcError::new(
+                INVALID_REQUEST,
+                "invalid_request",
+                "jsonrpc must be \"2.0\"",
+            );
+            return Some(response(id.unwrap_or_default(), Err(error)));
+        }
+        Err(e) => {
+            let error = RpcError::new(INVALID_REQUEST, "invalid_request", e.to_string());
+            return Some(response(id.unwrap_or_default(), Err(error)));
+        }
+    };
+
+    let result = match request.method.as_str() {
+        "generate" => with_params(request.params, |params| engine.generate(params)),
+        "verify" => with_params(request.params, |params| engine.verify(params)),
+        "extract" => with_params(request.params, |params| engine.extract(params)),
+        "templates" => Ok(serde_json::to_value(Templates::all()).expect("templates serialize")),
+        method => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            "method_not_found",
+            format!("no method '{}'", method),
+        )),
+    };
+    id.map(|id| response(id, result))
+}
+
+/// Parse `params` and answer them with `method`
+fn with_params<P: for<'de> Deserialize<'de>, T: Serialize>(
+    params: Value,
+    method: impl FnOnce(P) -> Result<T, CallError>,
+) -> Result<Value, RpcError> {
+    let params = serde_json::from_value(params)
+        .map_err(|e| RpcError::new(INVALID_PARAMS, "invalid_params", e.to_string()))?;
+    let result = method(params)?;
+    Ok(serde_json::to_value(result).expect("results serialize"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    fn engine() -> Engine {
+        Engine::new(Config::default()).unwrap()
+    }
+
+    fn answer_line(line: &[u8]) -> Option<Value> {
+        answer(&engine(), line).map(|reply| serde_json::to_value(reply).unwrap())
+    }
+
+    fn ask(request: Value) -> Value {
+        answer_line(request.to_string().as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_methods() {
+        let reply = ask(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "generate",
+            "params": {"input": "hi", "options": {"escape_strategy": "unicode"}},
+        }));
+        assert_eq!(reply["id"], 1);
+        assert_eq!(reply["result"]["escape_strategy"], "unicode");
+        assert!(reply.get("error").is_none());
+
+        let source = reply["result"]["one_line"].as_str().unwrap().to_string() + "\n";
+        let reply = ask(json!({
+            "jsonrpc": "2.0",
+            "id": "v",
+            "method": "verify",
+            "params": {"source": source},
+        }));
+        assert_eq!(reply["id"], "v");
+        assert_eq!(reply["result"]["method"], "static");
+        assert_eq!(reply["result"]["reproduces"], true);
+
+        let reply = ask(json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "extract",
+            "params": {"source": source},
+        }));
+        assert_eq!(reply["result"], "hi");
+
+        let reply = ask(json!({"jsonrpc": "2.0", "id": null, "method": "templates"}));
+        assert_eq!(reply["id"], Value::Null);
+        assert_eq!(reply["result"]["languages"][0]["name"], "javascript");
+    }
+
+    #[test]
+    fn test_errors() {
+        for (request, code, kind) in [
+            (
+                json!({"jsonrpc": "2.0", "id": 1, "method": "generate", "params": {"input": ""}}),
+                CALL_FAILED,
+                "empty_input",
+            ),
+            (
+                json!({"jsonrpc": "2.0", "id": 1, "method": "generate",
+                       "params": {"input": "hi", "options": {"style": "gnu"}}}),
+                INVALID_PARAMS,
+                "invalid_option",
+            ),
+            (
+                json!({"jsonrpc": "2.0", "id": 1, "method": "generate", "params": {"text": "hi"}}),
+                INVALID_PARAMS,
+                "invalid_params",
+            ),
+            (
+                json!({"jsonrpc": "2.0", "id": 1, "method": "extract", "params": {"source": "1"}}),
+                CALL_FAILED,
+                "unrecognized_quine",
+            ),
+            (
+                json!({"jsonrpc": "2.0", "id": 1, "method": "verify",
+                       "params": {"source": "1", "method": "Runtime"}}),
+                INVALID_PARAMS,
+                "invalid_params",
+            ),
+            (
+                json!({"jsonrpc": "2.0", "id": 1, "method": "verify",
+                       "params": {"source": "1", "method": "runtime"}}),
+                CALL_FAILED,
+                "exec_disabled",
+            ),
+            (
+                json!({"jsonrpc": "2.0", "id": 1, "method": "shutdown"}),
+                METHOD_NOT_FOUND,
+                "method_not_found",
+            ),
+            (
+                json!({"jsonrpc": "1.0", "id": 1, "method": "templates"}),
+                INVALID_REQUEST,
+                "invalid_request",
+            ),
+            (json!({"id": 1}), INVALID_REQUEST, "invalid_request"),
+            (json!([]), INVALID_REQUEST, "invalid_request"),
+        ] {
+            let reply = ask(request.clone());
+            assert_eq!(reply["error"]["code"], code, "{}", request);
+            assert_eq!(reply["error"]["data"]["kind"], kind, "{}", request);
+            assert!(reply.get("result").is_none());
+        }
+
+        let reply = answer_line(b"{not json").unwrap();
+        assert_eq!(reply["error"]["code"], PARSE_ERROR);
+        assert_eq!(reply["id"], Value::Null);
+    }
+
+    #[test]
+    fn test_notifications_and_batches() {
+        let notification = json!({"jsonrpc": "2.0", "method": "templates"});
+        assert_eq!(answer_line(notification.to_string().as_bytes()), None);
+
+        let reply = ask(json!([
+            notification,
+            {"jsonrpc": "2.0", "id": 1, "method": "templates"},
+            {"jsonrpc": "2.0", "id": 2, "method": "nope"},
+        ]));
+        let replies = reply.as_array().unwrap();
+        assert_eq!(replies.len(), 2);
+        assert_eq!(replies[0]["id"], 1);
+        assert_eq!(replies[1]["error"]["code"], METHOD_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_answer_lines() {
+        let engine = Engine::new(Config {
+            max_input_size: Some(1),
+            ..Config::default()
+        })
+        .unwrap();
+        let oversized = "x".repeat(engine.max_request_size() + 1);
+        let input = format!(
+            "{}\n\n{}\n{}",
+            json!({"jsonrpc": "2.0", "id": 1, "method": "generate", "params": {"input": "a"}}),
+            oversized,
+            json!({"jsonrpc": "2.0", "id": 2, "method": "generate", "params": {"input": "ab"}}),
+        );
+        let mut output = Vec::new();
+        answer_lines(&engine, input.as_bytes(), &mut output).unwrap();
+
+        let replies: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(replies.len(), 3);
+        assert_eq!(replies[0]["result"]["stats"]["input_bytes"], 1);
+        assert_eq!(replies[1]["error"]["data"]["kind"], "request_too_large");
+        assert_eq!(replies[2]["error"]["data"]["kind"], "input_too_large");
+    }
+}
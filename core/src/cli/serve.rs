@@ -0,0 +1,598 @@
+//! `psychoquine serve`: a JSON HTTP API on localhost
+//!
+//! Exposes generation, verification and the template catalogue so the web
+//! UI can use the Rust engine outside the desktop app. Replies use the
+//! desktop app's envelope, `{"success", "data", "error"}`, with an HTTP
+//! status to match.
+//!
+//! The server only answers requests addressed to a loopback host name or
+//! a name given with `--allowed-host`, and POST bodies must be
+//! `application/json`. Other web pages can then neither reach it through
+//! DNS rebinding nor send it simple cross-origin requests; origins given
+//! with `--allow-origin` get CORS headers.
+//!
+//! `/verify` checks quines statically. Running them with the interpreter
+//! executes whatever the caller sent, so `"method": "runtime"` is refused
+//! unless the server was started with `--allow-exec`.
+
+use std::io::Read;
+use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroUsize;
+use std::process::ExitCode;
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use psychoquine_core::{Config, QuineError, Templates};
+
+use super::api::{CallError, Engine};
+use super::args::{self, Arg, OptSpec};
+use super::commands;
+use super::error::CliError;
+use super::report::ErrorRecord;
+
+pub const OPTIONS: &[OptSpec] = &[
+    OptSpec::flag(Some('h'), "help", "Show this help message"),
+    OptSpec::with_value(None, "host", "ADDR", "IP address to listen on").default("127.0.0.1"),
+    OptSpec::with_value(Some('p'), "port", "PORT", "Port to listen on").default("7878"),
+    OptSpec::with_value(
+        None,
+        "allow-origin",
+        "ORIGIN",
+        "Allow cross-origin calls from ORIGIN (repeatable)",
+    ),
+    OptSpec::with_value(
+        None,
+        "allowed-host",
+        "NAME",
+        "Also answer requests addressed to NAME, besides localhost (repeatable)",
+    ),
+    OptSpec::flag(
+        None,
+        "allow-exec",
+        "Let /verify run submitted quines with the interpreter",
+    ),
+];
+
+#[derive(Default)]
+struct Args {
+    help: bool,
+    host: Option<String>,
+    port: Option<String>,
+    allowed_origins: Vec<String>,
+    allowed_hosts: Vec<String>,
+    allow_exec: bool,
+}
+
+fn parse_args(argv: &[String]) -> Result<Args, CliError> {
+    let mut args = Args::default();
+
+    for arg in args::parse(argv, OPTIONS)? {
+        match arg {
+            Arg::Opt("help", _) => args.help = true,
+            Arg::Opt("host", value) => args.host = value,
+            Arg::Opt("port", value) => args.port = value,
+            Arg::Opt("allow-origin", Some(origin)) => args.allowed_origins.push(origin),
+            Arg::Opt("allowed-host", Some(host)) => args.allowed_hosts.push(host),
+            Arg::Opt("allow-exec", _) => args.allow_exec = true,
+            Arg::Opt(other, _) => unreachable!("option --{} has no handler", other),
+            Arg::Positional(arg) => {
+                return Err(CliError::Usage(format!("unexpected argument '{}'", arg)))
+            }
+        }
+    }
+
+    Ok(args)
+}
+
+/// Run `psychoquine serve`
+pub fn run(argv: &[String]) -> ExitCode {
+    match serve(argv) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => e.report(),
+    }
+}
+
+fn serve(argv: &[String]) -> Result<(), CliError> {
+    let args = parse_args(argv)?;
+    if args.help {
+        commands::print_help("serve");
+        return Ok(());
+    }
+
+    let host = match args.host.as_deref() {
+        Some(host) => host.parse::<IpAddr>().map_err(|_| {
+            CliError::Usage(format!(
+                "invalid value '{}' for --host (expected an IP address)",
+                host
+            ))
+        })?,
+        None => IpAddr::from([127, 0, 0, 1]),
+    };
+    let port = match args.port.as_deref() {
+        Some(port) => port.parse::<u16>().map_err(|_| {
+            CliError::Usage(format!(
+                "invalid value '{}' for --port (expected 0 to 65535)",
+                port
+            ))
+        })?,
+        None => 7878,
+    };
+
+    let api = Api::new(
+        Config::discover()?
+            .map(|(_, config)| config)
+            .unwrap_or_default(),
+    )?
+    .with_allowed_origins(args.allowed_origins)
+    .with_allowed_hosts(args.allowed_hosts)
+    .with_exec(args.allow_exec);
+    let addr = SocketAddr::new(host, port);
+    let server = Server::http(addr)
+        .map_err(|e| CliError::Io(format!("cannot listen on {}: {}", addr, e)))?;
+    let addr = server.server_addr().to_ip().unwrap_or(addr);
+    eprintln!("Listening on http://{}", addr);
+
+    let workers = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                while let Ok(request) = server.recv() {
+                    if let Err(e) = api.respond(request) {
+                        eprintln!("cannot send reply: {}", e);
+                    }
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+/// The desktop app's response envelope
+#[derive(Debug, Serialize)]
+struct Envelope<T> {
+    success: bool,
+    data: Option<T>,
+    error: Option<ErrorRecord>,
+}
+
+/// An HTTP status and JSON body
+#[derive(Debug)]
+struct Reply {
+    status: u16,
+    body: String,
+}
+
+impl Reply {
+    fn data<T: Serialize>(data: T) -> Self {
+        Self::envelope(
+            200,
+            Envelope {
+                success: true,
+                data: Some(data),
+                error: None,
+            },
+        )
+    }
+
+    fn error(status: u16, kind: &str, message: impl Into<String>) -> Self {
+        Self::envelope(
+            status,
+            Envelope::<()> {
+                success: false,
+                data: None,
+                error: Some(ErrorRecord {
+                    kind: kind.to_string(),
+                    message: message.into(),
+                }),
+            },
+        )
+    }
+
+    fn envelope<T: Serialize>(status: u16, envelope: Envelope<T>) -> Self {
+        Self {
+            status,
+            body: serde_json::to_string(&envelope).expect("replies serialize"),
+        }
+    }
+}
+
+impl From<QuineError> for Reply {
+    fn from(error: QuineError) -> Self {
+        let status = match error {
+            QuineError::InputTooLarge { .. } => 413,
+            QuineError::InterpreterError(_) => 500,
+            QuineError::Timeout(_) => 504,
+            _ => 422,
+        };
+        Reply::error(status, error.kind(), error.to_string())
+    }
+}
+
+impl From<CallError> for Reply {
+    fn from(error: CallError) -> Self {
+        match error {
+            CallError::InvalidOption(e) => Reply::error(400, "invalid_option", e.to_string()),
+            CallError::ExecDisabled => Reply::error(
+                403,
+                error.kind(),
+                "runtime verification is disabled; start the server with --allow-exec",
+            ),
+            CallError::Quine(e) => e.into(),
+        }
+    }
+}
+
+/// What the server answers with, shared by its worker threads
+struct Api {
+    engine: Engine,
+    allowed_origins: Vec<String>,
+    /// Host names answered besides loopback ones
+    allowed_hosts: Vec<String>,
+}
+
+impl Api {
+    fn new(config: Config) -> Result<Self, QuineError> {
+        Ok(Self {
+            engine: Engine::new(config)?,
+            allowed_origins: Vec::new(),
+            allowed_hosts: Vec::new(),
+        })
+    }
+
+    fn with_allowed_origins(mut self, origins: Vec<String>) -> Self {
+        self.allowed_origins = origins;
+        self
+    }
+
+    /// Answer requests addressed to `hosts` as well as loopback names
+    fn with_allowed_hosts(mut self, hosts: Vec<String>) -> Self {
+        self.allowed_hosts = hosts;
+        self
+    }
+
+    /// Allow runtime verification, which runs submitted programs
+    fn with_exec(mut self, exec: bool) -> Self {
+        self.engine = self.engine.with_exec(exec);
+        self
+    }
+
+    /// Whether a `Host` header names this server
+    fn is_allowed_host(&self, host: &str) -> bool {
+        let name = host_name(host);
+        is_loopback_host(host)
+            || self
+                .allowed_hosts
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(name))
+    }
+
+    fn respond(&self, mut request: Request) -> std::io::Result<()> {
+        let method = request.method().clone();
+        let url = request.url().to_string();
+        let origin = header(&request, "Origin")
+            .filter(|origin| self.allowed_origins.iter().any(|allowed| allowed == origin))
+            .map(str::to_string);
+
+        let reply = if !header(&request, "Host").is_some_and(|host| self.is_allowed_host(host)) {
+            Reply::error(
+                403,
+                "forbidden",
+                "requests must be addressed to an allowed host",
+            )
+        } else if method == Method::Options {
+            // CORS preflight; the headers below carry the answer
+            Reply {
+                status: 204,
+                body: String::new(),
+            }
+        } else if method == Method::Post
+            && !header(&request, "Content-Type")
+                .is_some_and(|kind| kind.starts_with("application/json"))
+        {
+            Reply::error(
+                415,
+                "unsupported_media_type",
+                "request bodies must be application/json",
+            )
+        } else {
+            match self.read_body(&mut request) {
+                Ok(body) => self.route(&method, &url, &body),
+                Err(reply) => reply,
+            }
+        };
+        eprintln!("{} {} {}", method, url, reply.status);
+
+        let mut response = Response::from_string(reply.body)
+            .with_status_code(reply.status)
+            .with_header(header_line("Content-Type", "application/json"));
+        if let Some(origin) = origin {
+            response.add_header(header_line("Access-Control-Allow-Origin", &origin));
+            response.add_header(header_line("Access-Control-Allow-Methods", "GET, POST"));
+            response.add_header(header_line("Access-Control-Allow-Headers", "Content-Type"));
+            response.add_header(header_line("Vary", "Origin"));
+        }
+        request.respond(response)
+    }
+
+    fn read_body(&self, request: &mut Request) -> Result<String, Reply> {
+        let max = self.engine.max_request_size();
+        let mut body = Vec::new();
+        request
+            .as_reader()
+            .take(max.saturating_add(1) as u64)
+            .read_to_end(&mut body)
+            .map_err(|e| {
+                Reply::error(400, "invalid_request", format!("cannot read body: {}", e))
+            })?;
+        if body.len() > max {
+            return Err(Reply::error(
+                413,
+                "request_too_large",
+                format!("request bodies are limited to {} bytes", max),
+            ));
+        }
+        String::from_utf8(body).map_err(|_| Reply::from(QuineError::InvalidUtf8))
+    }
+
+    /// Answer an API call
+    fn route(&self, method: &Method, url: &str, body: &str) -> Reply {
+        let path = url.split('?').next().unwrap_or(url);
+        match (method, path) {
+            (Method::Get, "/templates") => Reply::data(Templates::all()),
+            (Method::Post, "/generate") => self.call(body, |body| self.engine.generate(body)),
+            (Method::Post, "/verify") => self.call(body, |body| self.engine.verify(body)),
+            (_, "/templates" | "/generate" | "/verify") => Reply::error(
+                405,
+                "method_not_allowed",
+                format!("{} does not accept {}", path, method),
+            ),
+            _ => Reply::error(404, "not_found", format!("no endpoint {}", path)),
+        }
+    }
+
+    /// Parse a body and answer it with `call`
+    fn call<'a, P: Deserialize<'a>, T: Serialize>(
+        &self,
+        body: &'a str,
+        call: impl FnOnce(P) -> Result<T, CallError>,
+    ) -> Reply {
+        match serde_json::from_str(body) {
+            Ok(params) => call(params).map_or_else(Reply::from, Reply::data),
+            Err(e) => Reply::error(400, "invalid_request", e.to_string()),
+        }
+    }
+}
+
+/// Value of the request header `name`
+fn header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str())
+}
+
+fn header_line(name: &str, value: &str) -> Header {
+    Header::from_bytes(name, value).expect("header is ASCII")
+}
+
+/// The name in a `Host` header, without its port
+fn host_name(host: &str) -> &str {
+    match host.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().unwrap_or(rest),
+        None => host.rsplit_once(':').map_or(host, |(name, _)| name),
+    }
+}
+
+/// Whether a `Host` header names this machine: `localhost` or a loopback
+/// address, with or without a port
+fn is_loopback_host(host: &str) -> bool {
+    let name = host_name(host);
+    name.eq_ignore_ascii_case("localhost")
+        || name.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write;
+    use std::net::TcpStream;
+
+    use psychoquine_core::generate;
+    use serde_json::Value;
+
+    fn api() -> Api {
+        Api::new(Config::default()).unwrap()
+    }
+
+    fn call(method: Method, url: &str, body: &str) -> (u16, Value) {
+        let reply = api().route(&method, url, body);
+        (reply.status, serde_json::from_str(&reply.body).unwrap())
+    }
+
+    #[test]
+    fn test_routes() {
+        let (status, reply) = call(
+            Method::Post,
+            "/generate",
+            r#"{"input": "hi", "options": {"escape_strategy": "unicode"}}"#,
+        );
+        assert_eq!(status, 200);
+        assert_eq!(reply["success"], true);
+        assert_eq!(reply["data"]["escape_strategy"], "unicode");
+
+        let source = reply["data"]["one_line"].as_str().unwrap().to_string() + "\n";
+        let body = serde_json::json!({ "source": source, "method": "static" });
+        let (status, reply) = call(Method::Post, "/verify", &body.to_string());
+        assert_eq!(status, 200);
+        assert_eq!(reply["data"]["reproduces"], true);
+
+        let (status, reply) = call(Method::Get, "/templates?x=1", "");
+        assert_eq!(status, 200);
+        assert_eq!(reply["data"]["languages"][0]["name"], "javascript");
+    }
+
+    #[test]
+    fn test_requests_cannot_raise_max_input_size() {
+        let api = Api::new(Config {
+            max_input_size: Some(4),
+            ..Config::default()
+        })
+        .unwrap();
+        let body = r#"{"input": "hello", "options": {"max_input_size": 1000}}"#;
+        assert_eq!(api.route(&Method::Post, "/generate", body).status, 413);
+        let body = r#"{"input": "hey", "options": {"max_input_size": 2}}"#;
+        assert_eq!(api.route(&Method::Post, "/generate", body).status, 413);
+        let body = r#"{"input": "hey", "options": {"max_input_size": 1000}}"#;
+        assert_eq!(api.route(&Method::Post, "/generate", body).status, 200);
+    }
+
+    #[test]
+    fn test_verify_never_runs_by_default() {
+        let source = generate("hi").unwrap().one_line + "\n";
+        let body = serde_json::json!({ "source": source }).to_string();
+        // Without a method, even a server allowing exec checks statically
+        for api in [api(), api().with_exec(true)] {
+            let reply = api.route(&Method::Post, "/verify", &body);
+            let reply: Value = serde_json::from_str(&reply.body).unwrap();
+            assert_eq!(reply["data"]["method"], "static");
+            assert_eq!(reply["data"]["reproduces"], true);
+        }
+
+        let body = serde_json::json!({ "source": source, "method": "runtime" }).to_string();
+        let reply = api().route(&Method::Post, "/verify", &body);
+        assert_eq!(reply.status, 403);
+        let reply: Value = serde_json::from_str(&reply.body).unwrap();
+        assert_eq!(reply["error"]["kind"], "exec_disabled");
+    }
+
+    #[test]
+    fn test_error_replies() {
+        for (method, url, body, status, kind) in [
+            (
+                Method::Post,
+                "/generate",
+                r#"{"input": ""}"#,
+                422,
+                "empty_input",
+            ),
+            (
+                Method::Post,
+                "/generate",
+                r#"{"input": "hi", "options": {"style": "gnu"}}"#,
+                400,
+                "invalid_option",
+            ),
+            (
+                Method::Post,
+                "/generate",
+                r#"{"input": "hi", "options": {"indent": "\u0001 "}}"#,
+                400,
+                "invalid_option",
+            ),
+            (
+                Method::Post,
+                "/generate",
+                r#"{"text": "hi"}"#,
+                400,
+                "invalid_request",
+            ),
+            (
+                Method::Post,
+                "/generate",
+                "not json",
+                400,
+                "invalid_request",
+            ),
+            (
+                Method::Post,
+                "/verify",
+                r#"{"source": "1", "method": "static"}"#,
+                422,
+                "unrecognized_quine",
+            ),
+            (Method::Get, "/generate", "", 405, "method_not_allowed"),
+            (Method::Get, "/", "", 404, "not_found"),
+        ] {
+            let (actual, reply) = call(method, url, body);
+            assert_eq!(actual, status, "{} {}", url, body);
+            assert_eq!(reply["success"], false);
+            assert_eq!(reply["error"]["kind"], kind, "{} {}", url, body);
+        }
+    }
+
+    #[test]
+    fn test_loopback_hosts() {
+        for host in [
+            "localhost",
+            "localhost:7878",
+            "127.0.0.1:80",
+            "[::1]:7878",
+            "[::1]",
+        ] {
+            assert!(is_loopback_host(host), "{}", host);
+        }
+        for host in ["example.com", "evil.localhost.example:7878", "192.168.1.2"] {
+            assert!(!is_loopback_host(host), "{}", host);
+        }
+
+        let api = api().with_allowed_hosts(vec!["Quines.example".to_string()]);
+        for host in ["quines.example", "quines.example:7878", "localhost"] {
+            assert!(api.is_allowed_host(host), "{}", host);
+        }
+        for host in ["example.com", "quines.example.evil:7878", "192.168.1.2"] {
+            assert!(!api.is_allowed_host(host), "{}", host);
+        }
+    }
+
+    /// Send one raw HTTP request to a fresh server and return the reply
+    fn exchange(api: Api, request: &str) -> String {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr().to_ip().unwrap();
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        api.respond(server.recv().unwrap()).unwrap();
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).unwrap();
+        reply
+    }
+
+    #[test]
+    fn test_requests_over_http() {
+        let body = r#"{"input": "hi"}"#;
+        let reply = exchange(
+            api().with_allowed_origins(vec!["http://localhost:8000".to_string()]),
+            &format!(
+                "POST /generate HTTP/1.1\r\nHost: localhost\r\nOrigin: http://localhost:8000\r\n\
+                 Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            ),
+        );
+        assert!(reply.starts_with("HTTP/1.1 200"), "{}", reply);
+        assert!(reply.contains("Access-Control-Allow-Origin: http://localhost:8000"));
+
+        let reply = exchange(
+            api(),
+            "POST /generate HTTP/1.1\r\nHost: localhost\r\nContent-Type: text/plain\r\n\
+             Content-Length: 2\r\nConnection: close\r\n\r\nhi",
+        );
+        assert!(reply.starts_with("HTTP/1.1 415"), "{}", reply);
+
+        let reply = exchange(
+            api(),
+            "GET /templates HTTP/1.1\r\nHost: rebound.example\r\nConnection: close\r\n\r\n",
+        );
+        assert!(reply.starts_with("HTTP/1.1 403"), "{}", reply);
+        assert!(!reply.contains("Access-Control-Allow-Origin"));
+
+        let reply = exchange(
+            api().with_allowed_hosts(vec!["quines.example".to_string()]),
+            "GET /templates HTTP/1.1\r\nHost: quines.example:7878\r\nConnection: close\r\n\r\n",
+        );
+        assert!(reply.starts_with("HTTP/1.1 200"), "{}", reply);
+    }
+}
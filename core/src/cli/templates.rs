@@ -0,0 +1,47 @@
+//! `psychoquine templates`: list what quines can be generated with
+
+use std::process::ExitCode;
+
+use psychoquine_core::Templates;
+
+use super::args::{self, Arg};
+use super::commands::{self, HELP_OPTIONS};
+use super::error::CliError;
+
+/// Run `psychoquine templates`
+pub fn run(argv: &[String]) -> ExitCode {
+    let parsed = match args::parse(argv, HELP_OPTIONS) {
+        Ok(parsed) => parsed,
+        Err(e) => return e.report(),
+    };
+    if let Some(Arg::Positional(arg)) = parsed.iter().find(|arg| matches!(arg, Arg::Positional(_)))
+    {
+        return CliError::Usage(format!("unexpected argument '{}'", arg)).report();
+    }
+    if !parsed.is_empty() {
+        commands::print_help("templates");
+        return ExitCode::SUCCESS;
+    }
+
+    let templates = Templates::all();
+    println!("LANGUAGES:");
+    for language in &templates.languages {
+        println!(
+            "    {:<14}interpreter: {}",
+            language.name,
+            language.interpreter.join(" ")
+        );
+    }
+    println!();
+    println!("ESCAPE STRATEGIES:");
+    for strategy in &templates.escape_strategies {
+        println!("    {:<14}{}", strategy.name, strategy.description);
+    }
+    println!();
+    println!("STYLE PROFILES:");
+    for name in &templates.styles {
+        println!("    {}", name);
+    }
+
+    ExitCode::SUCCESS
+}
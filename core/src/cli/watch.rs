@@ -0,0 +1,261 @@
+//! `psychoquine watch`: regenerate quines whenever their inputs change
+//!
+//! Inputs are watched through their directories (inotify on Linux), since
+//! editors often save by replacing the file rather than writing to it.
+//! Quines are named as in `batch`.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use notify::{EventKind, RecursiveMode, Watcher};
+
+use psychoquine_core::{verify_with_interpreter, QuineOutput, QuineStats};
+
+use super::args::{self, Arg, OptSpec};
+use super::batch::{self, Job};
+use super::commands;
+use super::error::CliError;
+use super::options::{Generation, GenerationFlags, GENERATION_OPTIONS};
+
+/// How long to wait for more events before regenerating, so a burst of
+/// writes from one save triggers one regeneration
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+pub const OPTIONS: &[OptSpec] = &[
+    OptSpec::flag(Some('h'), "help", "Show this help message"),
+    OptSpec::flag(
+        None,
+        "verify",
+        "Run each regenerated quine and check its output",
+    ),
+    OptSpec::with_value(
+        None,
+        "out-dir",
+        "DIR",
+        "Write quines into DIR instead of next to the inputs",
+    ),
+];
+
+#[derive(Default)]
+struct Args {
+    help: bool,
+    verify: bool,
+    generation: GenerationFlags,
+    out_dir: Option<String>,
+    paths: Vec<String>,
+}
+
+fn parse_args(argv: &[String]) -> Result<Args, CliError> {
+    let mut args = Args::default();
+
+    for arg in args::parse(argv, &[GENERATION_OPTIONS, OPTIONS].concat())? {
+        if args.generation.accept(&arg) {
+            continue;
+        }
+        match arg {
+            Arg::Opt("help", _) => args.help = true,
+            Arg::Opt("verify", _) => args.verify = true,
+            Arg::Opt("out-dir", value) => args.out_dir = value,
+            Arg::Opt(other, _) => unreachable!("option --{} has no handler", other),
+            Arg::Positional(path) => args.paths.push(path),
+        }
+    }
+
+    Ok(args)
+}
+
+/// Run `psychoquine watch`
+pub fn run(argv: &[String]) -> ExitCode {
+    match watch(argv) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => e.report(),
+    }
+}
+
+/// A watched input and the stats of its last successful generation
+struct Watched {
+    job: Job,
+    /// Path events for this input are reported under
+    key: PathBuf,
+    last: Option<QuineStats>,
+}
+
+fn watch(argv: &[String]) -> Result<(), CliError> {
+    let args = parse_args(argv)?;
+
+    if args.help {
+        commands::print_help("watch");
+        return Ok(());
+    }
+    if args.paths.is_empty() {
+        return Err(CliError::Usage(
+            "missing PATH\nUsage: psychoquine watch [OPTIONS] PATH...".to_string(),
+        ));
+    }
+
+    let generation = args.generation.resolve()?;
+    let mut inputs = Vec::new();
+    for path in &args.paths {
+        let metadata =
+            fs::metadata(path).map_err(|e| CliError::io(format!("cannot read {}", path), e))?;
+        if !metadata.is_file() {
+            return Err(CliError::Usage(format!("{} is not a file", path)));
+        }
+        inputs.push(PathBuf::from(path));
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)
+        .map_err(|e| CliError::Io(format!("cannot watch files: {}", e)))?;
+
+    let mut watched = Vec::new();
+    let mut dirs = BTreeSet::new();
+    for job in batch::jobs(inputs, args.out_dir.as_deref(), &generation)? {
+        let dir = job.input.parent().unwrap_or(Path::new(""));
+        let dir = fs::canonicalize(if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        })
+        .map_err(|e| CliError::io(format!("cannot read {}", dir.display()), e))?;
+        let key = dir.join(job.input.file_name().unwrap_or_default());
+        dirs.insert(dir);
+        watched.push(Watched {
+            job,
+            key,
+            last: None,
+        });
+    }
+    for dir in &dirs {
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(|e| CliError::Io(format!("cannot watch {}: {}", dir.display(), e)))?;
+    }
+
+    for input in &mut watched {
+        regenerate(&generation, input, args.verify);
+    }
+    eprintln!("Watching {} file(s); press Ctrl-C to stop.", watched.len());
+
+    while let Ok(event) = receiver.recv() {
+        let mut changed = BTreeSet::new();
+        let mut next = Some(event);
+        // Collect every event of this burst before regenerating
+        while let Some(event) = next {
+            match event {
+                Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                    for path in &event.paths {
+                        if let Some(index) = watched.iter().position(|w| &w.key == path) {
+                            changed.insert(index);
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("error: watching files: {}", e),
+            }
+            next = receiver.recv_timeout(DEBOUNCE).ok();
+        }
+
+        for index in changed {
+            // A replaced file may not exist yet; the create event follows
+            if watched[index].job.input.is_file() {
+                regenerate(&generation, &mut watched[index], args.verify);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Regenerate one input's quines and print what changed
+fn regenerate(generation: &Generation, watched: &mut Watched, verify: bool) {
+    let input = watched.job.input.display();
+    match batch::process(generation, &watched.job) {
+        Ok((output, _)) => {
+            println!(
+                "{}: {}",
+                input,
+                stats_diff(watched.last.as_ref(), &output.stats)
+            );
+            if verify {
+                check(generation, &watched.job, &output);
+            }
+            watched.last = Some(output.stats);
+        }
+        Err(e) => println!("{}: error: {}", input, e),
+    }
+    io::stdout().flush().ok();
+}
+
+/// Run each written quine and report whether it reproduces itself
+fn check(generation: &Generation, job: &Job, output: &QuineOutput) {
+    for (which, path) in &job.outputs {
+        let quine = output.source(*which);
+        match verify_with_interpreter(&quine, generation.language) {
+            Ok(report) => match report.first_mismatch {
+                None => println!("  {}: verified", path.display()),
+                Some(offset) => println!(
+                    "  {}: does not reproduce itself (differs at byte {})",
+                    path.display(),
+                    offset
+                ),
+            },
+            Err(e) => println!("  {}: cannot verify: {}", path.display(), e),
+        }
+    }
+}
+
+/// Describe new stats, relative to the previous ones if there are any
+fn stats_diff(old: Option<&QuineStats>, new: &QuineStats) -> String {
+    fn bytes(old: Option<usize>, new: usize) -> String {
+        match old {
+            Some(old) if old != new => {
+                format!("{} -> {} ({:+})", old, new, new as i64 - old as i64)
+            }
+            _ => new.to_string(),
+        }
+    }
+
+    let ratio = match old {
+        Some(old) if (old.expansion_ratio - new.expansion_ratio).abs() >= 0.005 => {
+            format!("{:.2}x -> {:.2}x", old.expansion_ratio, new.expansion_ratio)
+        }
+        _ => format!("{:.2}x", new.expansion_ratio),
+    };
+    format!(
+        "input {} bytes, one-line {}, multi-line {}, ratio {}",
+        bytes(old.map(|s| s.input_bytes), new.input_bytes),
+        bytes(old.map(|s| s.one_line_bytes), new.one_line_bytes),
+        bytes(old.map(|s| s.multi_line_bytes), new.multi_line_bytes),
+        ratio
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_diff() {
+        let stats = |input_bytes, one_line_bytes| QuineStats {
+            input_bytes,
+            one_line_bytes,
+            multi_line_bytes: 300,
+            expansion_ratio: one_line_bytes as f64 / input_bytes as f64,
+        };
+        assert_eq!(
+            stats_diff(None, &stats(10, 200)),
+            "input 10 bytes, one-line 200, multi-line 300, ratio 20.00x"
+        );
+        assert_eq!(
+            stats_diff(Some(&stats(10, 200)), &stats(8, 196)),
+            "input 10 -> 8 (-2) bytes, one-line 200 -> 196 (-4), multi-line 300, \
+             ratio 20.00x -> 24.50x"
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! Project configuration (`psychoquine.toml`)
+//!
+//! Defaults for generation shared by the CLI and the desktop app. The file
+//! is looked up in the current directory, then in
+//! `$XDG_CONFIG_HOME/psychoquine/` (`~/.config/psychoquine/` when unset).
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::formatter::FormatOptions;
+use crate::generator::{QuineError, QuineGenerator};
+use crate::request::GenerateRequest;
+
+/// Name of the configuration file
+pub const CONFIG_FILE_NAME: &str = "psychoquine.toml";
+
+/// Indentation for multi-line output: a number of spaces or a literal string
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Indent {
+    Spaces(usize),
+    Text(String),
+}
+
+/// Contents of a `psychoquine.toml` file
+///
+/// Every setting is optional; unset ones keep the built-in default.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Escape strategy name (`standard`, `unicode`, `hex`, `raw`)
+    pub escape_strategy: Option<String>,
+    /// Indentation for multi-line output
+    pub indent: Option<Indent>,
+    /// Output format name (`one-line`, `multi-line`, `both`)
+    pub output_format: Option<String>,
+    /// Target language name
+    pub language: Option<String>,
+    /// Maximum input size in bytes
+    pub max_input_size: Option<usize>,
+}
+
+impl Config {
+    /// Parse configuration from TOML text
+    pub fn from_toml(text: &str) -> Result<Self, QuineError> {
+        let config: Config =
+            toml::from_str(text).map_err(|e| QuineError::InvalidConfig(e.message().to_string()))?;
+        // Surface bad values now rather than on first use
+        config.format_options()?;
+        Ok(config)
+    }
+
+    /// Read and parse a configuration file
+    pub fn load(path: &Path) -> Result<Self, QuineError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| QuineError::InvalidConfig(format!("{}: {}", path.display(), e)))?;
+        Self::from_toml(&text).map_err(|e| {
+            QuineError::InvalidConfig(format!("{}: {}", path.display(), config_message(e)))
+        })
+    }
+
+    /// Paths searched for a configuration file, in order of precedence
+    pub fn search_paths() -> Vec<PathBuf> {
+        let mut paths = vec![PathBuf::from(CONFIG_FILE_NAME)];
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")));
+        if let Some(config_home) = config_home {
+            paths.push(config_home.join("psychoquine").join(CONFIG_FILE_NAME));
+        }
+        paths
+    }
+
+    /// Find and load the first configuration file on the search path
+    ///
+    /// Returns `Ok(None)` when there is no configuration file.
+    pub fn discover() -> Result<Option<(PathBuf, Self)>, QuineError> {
+        match Self::search_paths().into_iter().find(|path| path.is_file()) {
+            Some(path) => {
+                let config = Self::load(&path)?;
+                Ok(Some((path, config)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// The generation request this configuration describes
+    pub fn request(&self) -> GenerateRequest {
+        GenerateRequest {
+            escape_strategy: self.escape_strategy.clone(),
+            indent: self.indent.clone(),
+            output_format: self.output_format.clone(),
+            language: self.language.clone(),
+            max_input_size: self.max_input_size,
+            ..GenerateRequest::default()
+        }
+    }
+
+    /// Format options with this configuration applied over the defaults
+    pub fn format_options(&self) -> Result<FormatOptions, QuineError> {
+        Ok(self.request().format_options()?)
+    }
+
+    /// A generator using this configuration
+    pub fn generator(&self) -> Result<QuineGenerator, QuineError> {
+        Ok(self.request().generator()?)
+    }
+}
+
+/// Message of an `InvalidConfig` error without its prefix
+fn config_message(error: QuineError) -> String {
+    match error {
+        QuineError::InvalidConfig(message) => message,
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::escape::EscapeStrategy;
+    use crate::formatter::OutputFormat;
+    use crate::language::Language;
+
+    #[test]
+    fn test_config_applies_defaults() {
+        let config = Config::from_toml(
+            r#"
+            escape_strategy = "unicode"
+            indent = 2
+            output_format = "one-line"
+            language = "js"
+            max_input_size = 4
+            "#,
+        )
+        .unwrap();
+        let options = config.format_options().unwrap();
+
+        assert_eq!(options.escape_strategy, EscapeStrategy::Unicode);
+        assert_eq!(options.indent, "  ");
+        assert_eq!(options.output_format, OutputFormat::OneLine);
+        assert_eq!(options.language, Language::JavaScript);
+        assert!(matches!(
+            config.generator().unwrap().generate("too long"),
+            Err(QuineError::InputTooLarge { max: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn test_empty_config_keeps_defaults() {
+        let options = Config::from_toml("").unwrap().format_options().unwrap();
+        assert_eq!(options.indent, FormatOptions::default().indent);
+        assert_eq!(
+            Config::from_toml("indent = \"\\t\"").unwrap().indent,
+            Some(Indent::Text("\t".into()))
+        );
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        for text in [
+            "escape_strategy = \"rot13\"",
+            "unknown_key = 1",
+            "max_input_size = \"big\"",
+            // Accepted once, but nothing wraps multi-line output
+            "max_line_length = 100",
+            // Would change the program or be taken for a template hole
+            "indent = \"x\"",
+            "indent = \"\\u0001 \"",
+            "not toml",
+        ] {
+            assert!(
+                matches!(Config::from_toml(text), Err(QuineError::InvalidConfig(_))),
+                "{}",
+                text
+            );
+        }
+    }
+}
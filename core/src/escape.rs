@@ -2,10 +2,17 @@
 //!
 //! Provides strategies for escaping special characters in various contexts.
 
-use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::request::ParseOptionError;
 
 /// Strategy for escaping special characters in quine output
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+///
+/// Serialized by `name`, so JSON output can be sent back as a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum EscapeStrategy {
     /// Standard escape sequences (\\n, \\t, \\", etc.)
     #[default]
@@ -19,12 +26,58 @@ pub enum EscapeStrategy {
 }
 
 impl EscapeStrategy {
+    /// All escape strategies
+    pub const ALL: &'static [EscapeStrategy] = &[
+        EscapeStrategy::Standard,
+        EscapeStrategy::Unicode,
+        EscapeStrategy::Hexadecimal,
+        EscapeStrategy::Raw,
+    ];
+
+    /// Canonical lowercase name
+    pub fn name(&self) -> &'static str {
+        match self {
+            EscapeStrategy::Standard => "standard",
+            EscapeStrategy::Unicode => "unicode",
+            EscapeStrategy::Hexadecimal => "hex",
+            EscapeStrategy::Raw => "raw",
+        }
+    }
+
+    /// Look up a strategy by name, ignoring case; `hexadecimal` is
+    /// accepted for `hex`
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        if name == "hexadecimal" {
+            return Some(EscapeStrategy::Hexadecimal);
+        }
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|strategy| strategy.name() == name)
+    }
+
+    /// One-line description of the strategy
+    pub fn description(&self) -> &'static str {
+        match self {
+            EscapeStrategy::Standard => "Standard escape sequences (\\n, \\t, \\\", ...)",
+            EscapeStrategy::Unicode => "Unicode escapes (\\u{XXXX}) for everything non-printable-ASCII",
+            EscapeStrategy::Hexadecimal => "Hexadecimal escapes (\\xXX) where possible",
+            EscapeStrategy::Raw => "Minimal escaping, preserves most characters as-is",
+        }
+    }
+
     /// Escape a string according to the selected strategy
     pub fn escape(&self, input: &str) -> String {
+        self.escape_quoted(input, '"')
+    }
+
+    /// Escape a string for a literal delimited by `quote` (`"` or `'`)
+    pub fn escape_quoted(&self, input: &str, quote: char) -> String {
         match self {
             EscapeStrategy::Standard => Self::escape_standard(input),
-            EscapeStrategy::Unicode => Self::escape_unicode(input),
-            EscapeStrategy::Hexadecimal => Self::escape_hex(input),
+            EscapeStrategy::Unicode => Self::escape_unicode(input, quote),
+            EscapeStrategy::Hexadecimal => Self::escape_hex(input, quote),
             EscapeStrategy::Raw => Self::escape_raw(input),
         }
     }
@@ -49,12 +102,13 @@ impl EscapeStrategy {
         output
     }
 
-    fn escape_unicode(input: &str) -> String {
+    fn escape_unicode(input: &str, quote: char) -> String {
         let mut output = String::with_capacity(input.len() * 6);
         for ch in input.chars() {
             match ch {
                 '\\' => output.push_str("\\\\"),
                 '"' => output.push_str("\\\""),
+                '\'' if quote == '\'' => output.push_str("\\'"),
                 c if c.is_ascii_graphic() || c == ' ' => output.push(c),
                 c => output.push_str(&format!("\\u{{{:04x}}}", c as u32)),
             }
@@ -62,12 +116,13 @@ impl EscapeStrategy {
         output
     }
 
-    fn escape_hex(input: &str) -> String {
+    fn escape_hex(input: &str, quote: char) -> String {
         let mut output = String::with_capacity(input.len() * 4);
         for ch in input.chars() {
             match ch {
                 '\\' => output.push_str("\\\\"),
                 '"' => output.push_str("\\\""),
+                '\'' if quote == '\'' => output.push_str("\\'"),
                 c if c.is_ascii_graphic() || c == ' ' => output.push(c),
                 c if c as u32 <= 0xFF => {
                     output.push_str(&format!("\\x{:02x}", c as u32));
@@ -85,6 +140,122 @@ impl EscapeStrategy {
     }
 }
 
+impl fmt::Display for EscapeStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for EscapeStrategy {
+    type Err = ParseOptionError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::from_name(name).ok_or_else(|| {
+            let names = Self::ALL.iter().map(|strategy| strategy.name()).collect();
+            ParseOptionError::new("escape_strategy", name, names)
+        })
+    }
+}
+
+impl Serialize for EscapeStrategy {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for EscapeStrategy {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Encode a string as a double-quoted literal exactly as `JSON.stringify` would.
+///
+/// Quine templates rebuild their own data literal at runtime with
+/// `JSON.stringify`, so the literal embedded in the source must match it
+/// byte for byte.
+pub(crate) fn json_string_literal(input: &str) -> String {
+    let mut output = String::with_capacity(input.len() + 2);
+    output.push('"');
+    for ch in input.chars() {
+        match ch {
+            '\\' => output.push_str("\\\\"),
+            '"' => output.push_str("\\\""),
+            '\u{08}' => output.push_str("\\b"),
+            '\u{0c}' => output.push_str("\\f"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                output.push_str(&format!("\\u{:04x}", c as u32));
+            }
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+    output
+}
+
+/// Decode the body of a JavaScript string literal
+///
+/// Returns `None` for malformed escape sequences.
+pub(crate) fn unescape_js(body: &str) -> Option<String> {
+    let mut output = String::with_capacity(body.len());
+    let mut chars = body.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            output.push(ch);
+            continue;
+        }
+        match chars.next()? {
+            'n' => output.push('\n'),
+            'r' => output.push('\r'),
+            't' => output.push('\t'),
+            'b' => output.push('\u{08}'),
+            'f' => output.push('\u{0c}'),
+            'v' => output.push('\u{0b}'),
+            '0' => output.push('\0'),
+            // Line continuation
+            '\n' => {}
+            'x' => output.push(char::from_u32(take_hex(&mut chars, 2)?)?),
+            'u' => {
+                let code = if chars.peek() == Some(&'{') {
+                    chars.next();
+                    let hex: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                    u32::from_str_radix(&hex, 16).ok()?
+                } else {
+                    let unit = take_hex(&mut chars, 4)?;
+                    if (0xD800..0xDC00).contains(&unit) {
+                        // Surrogate pair spelled as two \uXXXX escapes
+                        if chars.next()? != '\\' || chars.next()? != 'u' {
+                            return None;
+                        }
+                        let low = take_hex(&mut chars, 4)?;
+                        0x10000 + ((unit - 0xD800) << 10) + (low.checked_sub(0xDC00)?)
+                    } else {
+                        unit
+                    }
+                };
+                output.push(char::from_u32(code)?);
+            }
+            other => output.push(other),
+        }
+    }
+
+    Some(output)
+}
+
+/// Read exactly `count` hex digits
+fn take_hex(chars: &mut impl Iterator<Item = char>, count: usize) -> Option<u32> {
+    let hex: String = chars.take(count).collect();
+    if hex.len() != count {
+        return None;
+    }
+    u32::from_str_radix(&hex, 16).ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -111,4 +282,32 @@ mod tests {
         assert_eq!(strategy.escape("hello"), "hello");
         assert_eq!(strategy.escape("hello\nworld"), "hello\\x0aworld");
     }
+
+    #[test]
+    fn test_single_quote_escape() {
+        let strategy = EscapeStrategy::Unicode;
+        assert_eq!(strategy.escape("it's"), "it's");
+        assert_eq!(strategy.escape_quoted("it's", '\''), "it\\'s");
+        assert_eq!(EscapeStrategy::Hexadecimal.escape_quoted("'", '\''), "\\'");
+    }
+
+    #[test]
+    fn test_unescape_round_trip() {
+        let input = "tab\t \"q\" 'a' \\ \u{1} é 🦀\n";
+        for strategy in EscapeStrategy::ALL {
+            for quote in ['"', '\''] {
+                let escaped = strategy.escape_quoted(input, quote);
+                assert_eq!(unescape_js(&escaped).as_deref(), Some(input), "{:?}", strategy);
+            }
+        }
+        assert_eq!(unescape_js("\\ud83e\\udd80").as_deref(), Some("🦀"));
+        assert_eq!(unescape_js("bad\\x4"), None);
+    }
+
+    #[test]
+    fn test_json_string_literal() {
+        assert_eq!(json_string_literal("a\"b\\c"), r#""a\"b\\c""#);
+        assert_eq!(json_string_literal("x\ny\u{1}"), r#""x\ny\u0001""#);
+        assert_eq!(json_string_literal("caf\u{e9}"), "\"caf\u{e9}\"");
+    }
 }
@@ -0,0 +1,208 @@
+//! C API for embedding the generator in other languages (`ffi` feature)
+//!
+//! The header is `core/include/psychoquine.h`, generated by cbindgen from
+//! this module. Options are passed as a JSON `GenerateRequest`, the object
+//! the desktop app and the HTTP API take, so adding an option never
+//! changes the ABI. Errors are kept per thread for `pq_last_error`.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use crate::generator::QuineError;
+use crate::request::GenerateRequest;
+
+/// A generated quine, freed with `pq_output_free`
+#[repr(C)]
+pub struct PqOutput {
+    /// One-line quine, NUL-terminated UTF-8
+    pub one_line: *mut c_char,
+    /// Multi-line quine, NUL-terminated UTF-8
+    pub multi_line: *mut c_char,
+    /// Input size in bytes
+    pub input_bytes: usize,
+    /// One-line quine size in bytes
+    pub one_line_bytes: usize,
+    /// Multi-line quine size in bytes
+    pub multi_line_bytes: usize,
+    /// One-line size divided by input size
+    pub expansion_ratio: f64,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: Option<String>) {
+    let message = message
+        .map(|message| CString::new(message.replace('\0', "\\0")).expect("NUL bytes are replaced"));
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+}
+
+/// Generate a quine from `input_len` bytes of UTF-8 text at `input`
+///
+/// `options_json` is a JSON object such as `{"escape_strategy": "unicode"}`
+/// with the keys of `psychoquine.toml` plus `style` and `seed`, or NULL
+/// for the defaults. Returns NULL on failure; `pq_last_error` then says
+/// why.
+///
+/// # Safety
+///
+/// `input` must point to `input_len` readable bytes (it may be NULL when
+/// `input_len` is 0), and `options_json` must be NULL or a NUL-terminated
+/// string.
+#[no_mangle]
+pub unsafe extern "C" fn pq_generate(
+    input: *const u8,
+    input_len: usize,
+    options_json: *const c_char,
+) -> *mut PqOutput {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        generate(input, input_len, options_json)
+    }))
+    .unwrap_or_else(|_| Err("internal error: generation panicked".to_string()));
+
+    match result {
+        Ok(output) => {
+            set_last_error(None);
+            Box::into_raw(Box::new(output))
+        }
+        Err(message) => {
+            set_last_error(Some(message));
+            ptr::null_mut()
+        }
+    }
+}
+
+unsafe fn generate(
+    input: *const u8,
+    input_len: usize,
+    options_json: *const c_char,
+) -> Result<PqOutput, String> {
+    let bytes = match input_len {
+        0 => &[][..],
+        _ if input.is_null() => return Err("input is NULL".to_string()),
+        _ => slice::from_raw_parts(input, input_len),
+    };
+    let input = std::str::from_utf8(bytes).map_err(|_| QuineError::InvalidUtf8.to_string())?;
+
+    let request: GenerateRequest = if options_json.is_null() {
+        GenerateRequest::default()
+    } else {
+        let options = CStr::from_ptr(options_json)
+            .to_str()
+            .map_err(|_| "options are not valid UTF-8".to_string())?;
+        serde_json::from_str(options).map_err(|e| format!("invalid options: {}", e))?
+    };
+    let output = request
+        .generator()
+        .map_err(|e| e.to_string())?
+        .generate(input)
+        .map_err(|e| e.to_string())?;
+
+    let one_line = CString::new(output.one_line)
+        .map_err(|_| "the one-line quine contains a NUL byte".to_string())?;
+    let multi_line = CString::new(output.multi_line)
+        .map_err(|_| "the multi-line quine contains a NUL byte".to_string())?;
+    Ok(PqOutput {
+        one_line: one_line.into_raw(),
+        multi_line: multi_line.into_raw(),
+        input_bytes: output.stats.input_bytes,
+        one_line_bytes: output.stats.one_line_bytes,
+        multi_line_bytes: output.stats.multi_line_bytes,
+        expansion_ratio: output.stats.expansion_ratio,
+    })
+}
+
+/// Free an output returned by `pq_generate`; NULL is ignored
+///
+/// # Safety
+///
+/// `output` must be NULL or a pointer returned by `pq_generate` that has
+/// not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn pq_output_free(output: *mut PqOutput) {
+    if output.is_null() {
+        return;
+    }
+    let output = Box::from_raw(output);
+    drop(CString::from_raw(output.one_line));
+    drop(CString::from_raw(output.multi_line));
+}
+
+/// Why the last `pq_generate` on this thread failed, or NULL if it
+/// succeeded
+///
+/// The message is NUL-terminated UTF-8 owned by the library. It stays
+/// valid until the next `pq_generate` call on the same thread.
+#[no_mangle]
+pub extern "C" fn pq_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn last_error() -> Option<String> {
+        let message = pq_last_error();
+        (!message.is_null()).then(|| {
+            unsafe { CStr::from_ptr(message) }
+                .to_str()
+                .unwrap()
+                .to_string()
+        })
+    }
+
+    #[test]
+    fn test_generate_and_free() {
+        let input = "hi\0there";
+        let options = CString::new(r#"{"escape_strategy": "unicode"}"#).unwrap();
+        unsafe {
+            let output = pq_generate(input.as_ptr(), input.len(), options.as_ptr());
+            assert!(!output.is_null());
+            assert_eq!(last_error(), None);
+
+            let expected = crate::generate_with_options(
+                input,
+                GenerateRequest {
+                    escape_strategy: Some("unicode".to_string()),
+                    ..GenerateRequest::default()
+                }
+                .format_options()
+                .unwrap(),
+            )
+            .unwrap();
+            let one_line = CStr::from_ptr((*output).one_line).to_str().unwrap();
+            assert_eq!(one_line, expected.one_line);
+            assert_eq!((*output).input_bytes, input.len());
+            assert_eq!((*output).multi_line_bytes, expected.stats.multi_line_bytes);
+            pq_output_free(output);
+            pq_output_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let options = CString::new(r#"{"escape_strategy": "rot13"}"#).unwrap();
+        unsafe {
+            assert!(pq_generate(ptr::null(), 0, ptr::null()).is_null());
+            assert_eq!(last_error().unwrap(), "Input is empty");
+
+            assert!(pq_generate(b"\xff".as_ptr(), 1, ptr::null()).is_null());
+            assert!(last_error().unwrap().contains("UTF-8"));
+
+            assert!(pq_generate(b"hi".as_ptr(), 2, options.as_ptr()).is_null());
+            assert!(last_error().unwrap().contains("'rot13'"));
+
+            assert!(pq_generate(ptr::null(), 2, ptr::null()).is_null());
+            assert_eq!(last_error().unwrap(), "input is NULL");
+        }
+    }
+}
@@ -3,8 +3,22 @@
 //! Handles the transformation of raw quine data into one-line
 //! and multi-line formatted representations.
 
+use std::fmt;
+use std::str::FromStr;
+
 use serde::{Deserialize, Serialize};
 use crate::escape::EscapeStrategy;
+use crate::generator::QuineError;
+use crate::header::HeaderOptions;
+use crate::style::{BraceStyle, StyleProfile};
+use crate::language::Language;
+use crate::request::ParseOptionError;
+use crate::variant::{Declaration, TemplateVariant, Wrapper};
+
+/// Marks where the self-referential data literal goes in a template
+pub(crate) const DATA_HOLE: char = '\0';
+/// Marks where the UTF-16 offset of the data literal goes in a template
+pub(crate) const OFFSET_HOLE: char = '\u{1}';
 
 /// Output format selection
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
@@ -18,19 +32,75 @@ pub enum OutputFormat {
     Both,
 }
 
+impl OutputFormat {
+    /// All output formats
+    pub const ALL: &'static [OutputFormat] =
+        &[OutputFormat::OneLine, OutputFormat::MultiLine, OutputFormat::Both];
+
+    /// Canonical lowercase name
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutputFormat::OneLine => "one-line",
+            OutputFormat::MultiLine => "multi-line",
+            OutputFormat::Both => "both",
+        }
+    }
+
+    /// Look up an output format by name, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        Self::ALL.iter().copied().find(|format| format.name() == name)
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = ParseOptionError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::from_name(name).ok_or_else(|| {
+            let names = Self::ALL.iter().map(|format| format.name()).collect();
+            ParseOptionError::new("output_format", name, names)
+        })
+    }
+}
+
+/// Whether `indent` is made only of spaces and tabs
+///
+/// Anything else would change the program, or be mistaken for one of the
+/// template's holes.
+pub(crate) fn is_valid_indent(indent: &str) -> bool {
+    indent.chars().all(|c| c == ' ' || c == '\t')
+}
+
 /// Configuration options for quine formatting
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FormatOptions {
     /// The escape strategy to use
     pub escape_strategy: EscapeStrategy,
-    /// Indentation string for multi-line output
+    /// Indentation string for multi-line output; only spaces and tabs
     pub indent: String,
-    /// Maximum line length for multi-line output (0 = no limit)
-    pub max_line_length: usize,
     /// Whether to include a trailing newline
     pub trailing_newline: bool,
     /// Output format selection
     pub output_format: OutputFormat,
+    /// Header comment emitted at the top of the quine
+    #[serde(default)]
+    pub header: HeaderOptions,
+    /// Code style for multi-line output
+    #[serde(default)]
+    pub style: StyleProfile,
+    /// Seed selecting an equivalent template spelling (None = canonical)
+    #[serde(default)]
+    pub variant_seed: Option<u64>,
+    /// Language the quine is generated in
+    #[serde(default)]
+    pub language: Language,
 }
 
 impl Default for FormatOptions {
@@ -38,9 +108,12 @@ impl Default for FormatOptions {
         Self {
             escape_strategy: EscapeStrategy::Standard,
             indent: String::from("    "),
-            max_line_length: 80,
             trailing_newline: true,
             output_format: OutputFormat::Both,
+            header: HeaderOptions::default(),
+            style: StyleProfile::default(),
+            variant_seed: None,
+            language: Language::default(),
         }
     }
 }
@@ -74,94 +147,264 @@ impl FormatOptions {
         self
     }
 
-    /// Set the maximum line length
-    pub fn with_max_line_length(mut self, length: usize) -> Self {
-        self.max_line_length = length;
+    /// Set the header comment
+    pub fn with_header(mut self, header: HeaderOptions) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Set the multi-line code style
+    pub fn with_style(mut self, style: StyleProfile) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Set the seed used to pick a template variant
+    pub fn with_variant_seed(mut self, seed: u64) -> Self {
+        self.variant_seed = Some(seed);
+        self
+    }
+
+    /// Set the target language
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.language = language;
         self
     }
 }
 
+/// A quine together with what is needed to check its fixed point
+pub(crate) struct FilledTemplate {
+    /// The quine source
+    pub source: String,
+    /// UTF-16 offset of the data literal within the source
+    pub offset: usize,
+}
+
+impl FilledTemplate {
+    /// Check that running the program would print its own source
+    ///
+    /// Reads the program the way `verify::verify` does, from the source
+    /// alone: the offset from the print statement, the data by decoding the
+    /// literal found there and the codec from the rebuild expression. A
+    /// literal that the emitted expression would not rebuild is caught, not
+    /// only one that disagrees with `self.variant`.
+    pub fn verify(&self) -> bool {
+        crate::verify::analyze(&self.source)
+            .is_ok_and(|analysis| analysis.literal_offset == self.offset)
+            && crate::verify::verify(&self.source).is_ok_and(|report| report.reproduces)
+    }
+}
+
+/// Convert a UTF-16 offset into a byte offset, if it falls on a char boundary
+pub(crate) fn utf16_to_byte_offset(text: &str, offset: usize) -> Option<usize> {
+    let mut units = 0;
+    for (index, ch) in text.char_indices() {
+        if units == offset {
+            return Some(index);
+        }
+        units += ch.len_utf16();
+    }
+    (units == offset).then_some(text.len())
+}
+
 /// Formatter for quine output
 pub struct Formatter {
     options: FormatOptions,
+    variant: TemplateVariant,
 }
 
 impl Formatter {
     /// Create a new formatter with the given options
     pub fn new(options: FormatOptions) -> Self {
-        Self { options }
+        let variant = options
+            .variant_seed
+            .map(TemplateVariant::from_seed)
+            .unwrap_or_default();
+        Self::with_variant(options, variant)
     }
 
-    /// Format the quine data as a single line
-    pub fn format_one_line(&self, _data: &str, escaped: &str) -> String {
-        let template = self.generate_quine_template(escaped);
-        template.replace('\n', "").replace("  ", " ")
+    /// Create a formatter using an explicit template variant
+    pub(crate) fn with_variant(options: FormatOptions, variant: TemplateVariant) -> Self {
+        Self { options, variant }
     }
 
-    /// Format the quine data with line breaks and indentation
-    pub fn format_multi_line(&self, _data: &str, escaped: &str) -> String {
-        let template = self.generate_quine_template(escaped);
-        self.apply_formatting(&template)
+    /// The options this formatter was created with
+    pub(crate) fn options(&self) -> &FormatOptions {
+        &self.options
     }
 
-    /// Generate the core quine template
-    fn generate_quine_template(&self, escaped_data: &str) -> String {
-        // Universal quine structure that works across languages
-        // The pattern: define data, then use data to print both code and data
-        format!(
-            r#"(function(){{var d="{}";var q=String.fromCharCode(34);console.log("(function(){{var d="+q+d+q+";"+d.slice(d.indexOf("var q")))}})()"#,
-            escaped_data
-        )
+    /// Format the quine data as a single line
+    pub fn format_one_line(&self, data: &str, escaped: &str) -> Result<String, QuineError> {
+        Ok(self.fill_one_line(data, escaped)?.source)
     }
 
-    fn apply_formatting(&self, code: &str) -> String {
-        let mut result = String::new();
-        let mut depth = 0;
-        let mut in_string = false;
-        let mut escape_next = false;
+    /// Format the quine data as a single line, keeping verification data
+    pub(crate) fn fill_one_line(
+        &self,
+        data: &str,
+        escaped: &str,
+    ) -> Result<FilledTemplate, QuineError> {
+        let header = self.options.header.render_one_line(data)?;
+        let template = |variant: &TemplateVariant| {
+            format!("{}{}", header, self.generate_one_line_template(variant, escaped))
+        };
+        Ok(self.fill_template(template))
+    }
 
-        for ch in code.chars() {
-            if escape_next {
-                result.push(ch);
-                escape_next = false;
-                continue;
-            }
+    /// Format the quine data with line breaks and indentation
+    pub fn format_multi_line(&self, data: &str, escaped: &str) -> Result<String, QuineError> {
+        if !is_valid_indent(&self.options.indent) {
+            return Err(QuineError::FormattingError(
+                "indent must be made of spaces and tabs".to_string(),
+            ));
+        }
+        let header = self.options.header.render_multi_line(data)?;
+        let quote = self.options.style.quote_style.as_char();
+        let escaped = if quote == '"' {
+            escaped.to_string()
+        } else {
+            self.options.escape_strategy.escape_quoted(data, quote)
+        };
+        let template = |variant: &TemplateVariant| {
+            format!("{}{}", header, self.generate_multi_line_template(variant, &escaped))
+        };
+        let mut result = self.fill_template(template).source;
 
-            match ch {
-                '\\' if in_string => {
-                    result.push(ch);
-                    escape_next = true;
-                }
-                '"' => {
-                    result.push(ch);
-                    in_string = !in_string;
-                }
-                '{' if !in_string => {
-                    depth += 1;
-                    result.push(ch);
-                    result.push('\n');
-                    result.push_str(&self.options.indent.repeat(depth));
-                }
-                '}' if !in_string => {
-                    depth = depth.saturating_sub(1);
-                    result.push('\n');
-                    result.push_str(&self.options.indent.repeat(depth));
-                    result.push(ch);
+        if self.options.trailing_newline {
+            result.push('\n');
+        }
+
+        Ok(result)
+    }
+
+    /// Statements making up the body of the quine, in source order
+    ///
+    /// The program source has a hole where its own data literal goes. At
+    /// runtime the program prints `d` with the literal re-inserted at the
+    /// hole, which reproduces the source exactly, header comment included.
+    fn template_statements(
+        style: &StyleProfile,
+        variant: &TemplateVariant,
+        payload_literal: &str,
+    ) -> Vec<String> {
+        let assign = style.op("=");
+        let plus = style.op("+");
+        let d = variant.data_ident;
+
+        let mut statements: Vec<String> = variant
+            .order
+            .iter()
+            .map(|decl| match decl {
+                Declaration::Data => variant.declaration(d, &assign, &DATA_HOLE.to_string()),
+                Declaration::Payload => {
+                    variant.declaration(variant.payload_ident, &assign, payload_literal)
                 }
-                ';' if !in_string => {
-                    result.push(ch);
-                    result.push('\n');
-                    result.push_str(&self.options.indent.repeat(depth));
+                Declaration::Quote => {
+                    variant.declaration(variant.quote_ident, &assign, variant.quote_expression())
                 }
-                _ => result.push(ch),
-            }
+            })
+            .collect();
+
+        statements.push(format!(
+            "console.log({d}.{slice}(0{comma}{offset}){plus}{literal}{plus}{d}.{slice}({offset}))",
+            d = d,
+            slice = variant.slice_method,
+            comma = style.comma(),
+            offset = OFFSET_HOLE,
+            plus = plus,
+            literal = variant.literal_expression(&plus),
+        ));
+        statements
+    }
+
+    /// Opening of the wrapper function, up to (not including) the brace
+    fn wrapper_head(style: &StyleProfile, variant: &TemplateVariant) -> &'static str {
+        match (variant.wrapper, style.spaces_around_operators) {
+            (Wrapper::Function, false) => "(function()",
+            (Wrapper::Function, true) => "(function ()",
+            (Wrapper::Arrow, false) => "(()=>",
+            (Wrapper::Arrow, true) => "(() =>",
+            (Wrapper::Bare, _) => "",
         }
+    }
 
-        if self.options.trailing_newline && !result.ends_with('\n') {
-            result.push('\n');
+    /// Generate the one-line quine template
+    fn generate_one_line_template(&self, variant: &TemplateVariant, escaped_data: &str) -> String {
+        let style = StyleProfile::compact();
+        let literal = format!("\"{}\"", escaped_data);
+        let body = Self::template_statements(&style, variant, &literal).join(";");
+        match variant.wrapper {
+            Wrapper::Bare => body,
+            _ => format!("{}{{{}}})()", Self::wrapper_head(&style, variant), body),
+        }
+    }
+
+    /// Generate the multi-line quine template, laid out by the style profile
+    fn generate_multi_line_template(
+        &self,
+        variant: &TemplateVariant,
+        escaped_data: &str,
+    ) -> String {
+        let style = &self.options.style;
+        let quote = style.quote_style.as_char();
+        let literal = format!("{}{}{}", quote, escaped_data, quote);
+        let statements = Self::template_statements(style, variant, &literal);
+
+        if variant.wrapper == Wrapper::Bare {
+            let mut code = statements.join(";\n");
+            code.push_str(style.trailing());
+            return code;
+        }
+
+        let mut code = String::from(Self::wrapper_head(style, variant));
+        match style.brace_style {
+            BraceStyle::KAndR if style.spaces_around_operators => code.push_str(" {\n"),
+            BraceStyle::KAndR => code.push_str("{\n"),
+            BraceStyle::Allman => code.push_str("\n{\n"),
         }
 
-        result
+        let last = statements.len() - 1;
+        for (i, statement) in statements.iter().enumerate() {
+            code.push_str(&self.options.indent);
+            code.push_str(statement);
+            code.push_str(if i == last { style.trailing() } else { ";" });
+            code.push('\n');
+        }
+
+        code.push_str("})()");
+        code.push_str(style.trailing());
+        code
+    }
+
+    /// Build a template with the formatter's variant and fill its holes
+    ///
+    /// The data string is the template with the hole removed; the source is
+    /// the template with the encoded literal of that data string in the
+    /// hole. Neither placeholder can survive escaping, and header text
+    /// containing one is rejected, so they only occur where the template
+    /// put them. If the variant cannot encode the data,
+    /// the template is rebuilt with the JSON codec.
+    fn fill_template<F>(&self, build: F) -> FilledTemplate
+    where
+        F: Fn(&TemplateVariant) -> String,
+    {
+        let mut variant = self.variant.clone();
+        let mut template = build(&variant);
+        if !variant.supports(&template.replace([DATA_HOLE, OFFSET_HOLE], "")) {
+            variant = variant.with_json_codec();
+            template = build(&variant);
+        }
+
+        let (prefix, suffix) = template
+            .split_once(DATA_HOLE)
+            .expect("quine template must contain a data hole");
+        let offset = prefix.encode_utf16().count();
+        let suffix = suffix.replace(OFFSET_HOLE, &offset.to_string());
+        let data = format!("{}{}", prefix, suffix);
+        FilledTemplate {
+            source: format!("{}{}{}", prefix, variant.encode(&data), suffix),
+            offset,
+        }
     }
 }
 
@@ -173,14 +416,180 @@ mod tests {
     fn test_default_options() {
         let options = FormatOptions::default();
         assert_eq!(options.indent, "    ");
-        assert_eq!(options.max_line_length, 80);
         assert!(options.trailing_newline);
     }
 
+    /// Evaluate the quine the way the JavaScript runtime would
+    ///
+    /// Locates the data literal via the offset in the print statement,
+    /// decodes it and re-inserts the encoding the program would compute.
+    fn reproduce(source: &str) -> String {
+        let call = source.rfind("(0,").unwrap() + "(0,".len();
+        let digits: String = source[call..]
+            .trim_start()
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        let offset: usize = digits.parse().unwrap();
+
+        let units: Vec<u16> = source.encode_utf16().collect();
+        let start = String::from_utf16(&units[..offset]).unwrap().len();
+        let mut stream = serde_json::Deserializer::from_str(&source[start..]).into_iter();
+        let data: String = stream.next().unwrap().unwrap();
+
+        let literal = if source.contains("JSON.stringify") {
+            crate::escape::json_string_literal(&data)
+        } else {
+            let body = data
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("\"{}\"", body)
+        };
+        let units: Vec<u16> = data.encode_utf16().collect();
+        format!(
+            "{}{}{}",
+            String::from_utf16(&units[..offset]).unwrap(),
+            literal,
+            String::from_utf16(&units[offset..]).unwrap()
+        )
+    }
+
     #[test]
     fn test_formatter_one_line() {
         let formatter = Formatter::new(FormatOptions::one_line());
-        let result = formatter.format_one_line("test", "test");
-        assert!(!result.contains('\n') || result.ends_with('\n') && result.matches('\n').count() == 1);
+        let result = formatter.format_one_line("test", "test").unwrap();
+        assert!(!result.contains('\n'));
+    }
+
+    #[test]
+    fn test_quine_reproduces_itself() {
+        let formatter = Formatter::new(FormatOptions::default());
+        let one_line = formatter.format_one_line("a  b", "a  b").unwrap();
+        assert_eq!(reproduce(&one_line), one_line);
+        assert!(one_line.contains(r#"var p="a  b";"#));
+
+        let multi_line = formatter.format_multi_line("a  b", "a  b").unwrap();
+        assert_eq!(reproduce(&multi_line) + "\n", multi_line);
+    }
+
+    #[test]
+    fn test_style_profiles_reproduce_themselves() {
+        let payload = "it's \"quoted\"\n";
+        for name in StyleProfile::PRESETS {
+            for strategy in [EscapeStrategy::Standard, EscapeStrategy::Unicode] {
+                let options = FormatOptions::default()
+                    .with_style(StyleProfile::preset(name).unwrap())
+                    .with_escape_strategy(strategy);
+                let escaped = strategy.escape(payload);
+                let multi_line = Formatter::new(options)
+                    .format_multi_line(payload, &escaped)
+                    .unwrap();
+                assert_eq!(reproduce(&multi_line) + "\n", multi_line, "preset {}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_style_layout() {
+        let options = FormatOptions::default()
+            .with_indent("  ")
+            .with_style(StyleProfile::preset("allman").unwrap());
+        let multi_line = Formatter::new(options).format_multi_line("x", "x").unwrap();
+        assert!(multi_line.starts_with("(function ()\n{\n  var d = \""));
+        assert!(multi_line.contains("\n  var p = \"x\";\n"));
+        assert!(multi_line.contains("JSON.stringify(d) + d.slice("));
+        assert!(multi_line.ends_with(");\n})();\n"));
+
+        let options = FormatOptions::default().with_style(StyleProfile::preset("single-quote").unwrap());
+        let multi_line = Formatter::new(options).format_multi_line("x", "x").unwrap();
+        assert!(multi_line.contains("var p = 'x';"));
+    }
+
+    #[test]
+    fn test_variants_reproduce_themselves() {
+        let payload = "variant \"payload\"\\";
+        let escaped = EscapeStrategy::Standard.escape(payload);
+        let mut sources = Vec::new();
+        for seed in 0..64 {
+            for style in ["compact", "allman"] {
+                let options = FormatOptions::default()
+                    .with_style(StyleProfile::preset(style).unwrap())
+                    .with_variant_seed(seed);
+                let formatter = Formatter::new(options);
+                let one_line = formatter.format_one_line(payload, &escaped).unwrap();
+                assert_eq!(reproduce(&one_line), one_line, "seed {}", seed);
+                let multi_line = formatter.format_multi_line(payload, &escaped).unwrap();
+                assert_eq!(reproduce(&multi_line) + "\n", multi_line, "seed {}", seed);
+                sources.push(one_line);
+            }
+        }
+        sources.sort();
+        sources.dedup();
+        assert!(sources.len() > 60);
+    }
+
+    #[test]
+    fn test_golf_candidates_reproduce_themselves() {
+        let payload = "golf\n\"payload\"";
+        let escaped = EscapeStrategy::Standard.escape(payload);
+        for variant in TemplateVariant::golf_candidates() {
+            let options = FormatOptions::default().with_style(StyleProfile::standard());
+            let formatter = Formatter::with_variant(options, variant.clone());
+            let filled = formatter.fill_one_line(payload, &escaped).unwrap();
+            assert!(filled.verify(), "{:?}", variant);
+            assert_eq!(reproduce(&filled.source), filled.source);
+            let multi_line = formatter.format_multi_line(payload, &escaped).unwrap();
+            assert_eq!(reproduce(&multi_line) + "\n", multi_line, "{:?}", variant);
+        }
+    }
+
+    #[test]
+    fn test_invalid_indent_is_rejected() {
+        for indent in ["x", "\u{1} ", "\0"] {
+            let formatter = Formatter::new(FormatOptions::default().with_indent(indent));
+            assert!(matches!(
+                formatter.format_multi_line("x", "x"),
+                Err(QuineError::FormattingError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_verify_detects_broken_quine() {
+        let formatter = Formatter::new(FormatOptions::default());
+        let mut filled = formatter.fill_one_line("x", "x").unwrap();
+        assert!(filled.verify());
+        filled.source.push(' ');
+        assert!(!filled.verify());
+        filled.offset += 1;
+        assert!(!filled.verify());
+    }
+
+    #[test]
+    fn test_variant_falls_back_to_json_codec() {
+        for seed in 0..16 {
+            let options = FormatOptions::default().with_indent("\t").with_variant_seed(seed);
+            let multi_line = Formatter::new(options).format_multi_line("x", "x").unwrap();
+            assert!(multi_line.contains("JSON.stringify"));
+            assert_eq!(reproduce(&multi_line) + "\n", multi_line);
+        }
+    }
+
+    #[test]
+    fn test_header_survives_reproduction() {
+        let header = HeaderOptions::new()
+            .with_license("SPDX-License-Identifier: MIT\nCopyright (c) 2026 Überstudio")
+            .with_version()
+            .with_payload_hash();
+        let formatter = Formatter::new(FormatOptions::default().with_header(header));
+
+        let one_line = formatter.format_one_line("🦀", "🦀").unwrap();
+        assert!(one_line.starts_with("/* SPDX-License-Identifier: MIT | "));
+        assert_eq!(reproduce(&one_line), one_line);
+
+        let multi_line = formatter.format_multi_line("🦀", "🦀").unwrap();
+        assert!(multi_line.starts_with("/*\n * SPDX-License-Identifier: MIT\n"));
+        assert_eq!(reproduce(&multi_line) + "\n", multi_line);
     }
 }
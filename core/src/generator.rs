@@ -2,11 +2,17 @@
 //!
 //! Transforms arbitrary input text into self-replicating program structures.
 
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::escape::EscapeStrategy;
 use crate::formatter::{FormatOptions, Formatter, OutputFormat};
+use crate::header::HeaderOptions;
+use crate::language::Language;
+use crate::progress::{CancellationToken, Phase, Progress};
+use crate::style::StyleProfile;
 
 /// Errors that can occur during quine generation
 #[derive(Error, Debug)]
@@ -19,6 +25,33 @@ pub enum QuineError {
     InvalidUtf8,
     #[error("Formatting error: {0}")]
     FormattingError(String),
+    #[error("Not a recognized quine: {0}")]
+    UnrecognizedQuine(String),
+    #[error("Failed to run interpreter: {0}")]
+    InterpreterError(String),
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+    #[error("Generation was cancelled")]
+    Cancelled,
+    #[error("Interpreter did not finish within {0:?}")]
+    Timeout(Duration),
+}
+
+impl QuineError {
+    /// Stable snake_case identifier of the error variant, for machine-readable output
+    pub fn kind(&self) -> &'static str {
+        match self {
+            QuineError::EmptyInput => "empty_input",
+            QuineError::InputTooLarge { .. } => "input_too_large",
+            QuineError::InvalidUtf8 => "invalid_utf8",
+            QuineError::FormattingError(_) => "formatting_error",
+            QuineError::UnrecognizedQuine(_) => "unrecognized_quine",
+            QuineError::InterpreterError(_) => "interpreter_error",
+            QuineError::InvalidConfig(_) => "invalid_config",
+            QuineError::Cancelled => "cancelled",
+            QuineError::Timeout(_) => "timeout",
+        }
+    }
 }
 
 /// The output of quine generation
@@ -36,6 +69,24 @@ pub struct QuineOutput {
     pub stats: QuineStats,
 }
 
+impl QuineOutput {
+    /// Source of the one-line or multi-line quine, ending in exactly one
+    /// newline like the program's own output, ready to be written to a file
+    ///
+    /// `OutputFormat::Both` gives the multi-line quine.
+    pub fn source(&self, format: OutputFormat) -> String {
+        let mut quine = if format == OutputFormat::OneLine {
+            self.one_line.clone()
+        } else {
+            self.multi_line.clone()
+        };
+        if !quine.ends_with('\n') {
+            quine.push('\n');
+        }
+        quine
+    }
+}
+
 /// Statistics about the quine generation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QuineStats {
@@ -63,7 +114,10 @@ impl Default for QuineGenerator {
 
 impl QuineGenerator {
     /// Maximum input size (10 MB)
-    const DEFAULT_MAX_INPUT_SIZE: usize = 10 * 1024 * 1024;
+    pub const DEFAULT_MAX_INPUT_SIZE: usize = 10 * 1024 * 1024;
+
+    /// Bytes of input escaped between progress reports and cancellation checks
+    pub const PROGRESS_CHUNK_SIZE: usize = 64 * 1024;
 
     /// Create a new generator with default options
     pub fn new() -> Self {
@@ -87,9 +141,40 @@ impl QuineGenerator {
         self
     }
 
+    /// The maximum input size in bytes
+    pub fn max_input_size(&self) -> usize {
+        self.max_input_size
+    }
+
     /// Generate a quine from the input
     pub fn generate(&self, input: &str) -> Result<QuineOutput, QuineError> {
-        // Validation
+        self.validate(input)?;
+
+        // Create formatter
+        let formatter = Formatter::new(self.options.clone());
+
+        self.generate_with(input, &formatter)
+    }
+
+    /// Generate a quine, calling `on_progress` after each step and failing
+    /// with `QuineError::Cancelled` once `cancel` is cancelled
+    ///
+    /// Escaping is checked every `PROGRESS_CHUNK_SIZE` bytes, but building
+    /// each quine is a single step: a cancel while one is being built
+    /// takes effect when it is finished, before the next one starts.
+    pub fn generate_with_progress(
+        &self,
+        input: &str,
+        cancel: &CancellationToken,
+        mut on_progress: impl FnMut(Progress),
+    ) -> Result<QuineOutput, QuineError> {
+        self.validate(input)?;
+        let formatter = Formatter::new(self.options.clone());
+        self.generate_reporting(input, &formatter, cancel, &mut on_progress)
+    }
+
+    /// Check the input against the generator's limits
+    pub(crate) fn validate(&self, input: &str) -> Result<(), QuineError> {
         if input.is_empty() {
             return Err(QuineError::EmptyInput);
         }
@@ -101,15 +186,62 @@ impl QuineGenerator {
             });
         }
 
-        // Escape the input
-        let escaped = self.options.escape_strategy.escape(input);
+        Ok(())
+    }
 
-        // Create formatter
-        let formatter = Formatter::new(self.options.clone());
+    /// The formatting options used by this generator
+    pub fn options(&self) -> &FormatOptions {
+        &self.options
+    }
+
+    /// Generate a quine from validated input with a specific formatter
+    pub(crate) fn generate_with(
+        &self,
+        input: &str,
+        formatter: &Formatter,
+    ) -> Result<QuineOutput, QuineError> {
+        self.generate_reporting(input, formatter, &CancellationToken::new(), &mut |_| {})
+    }
+
+    fn generate_reporting(
+        &self,
+        input: &str,
+        formatter: &Formatter,
+        cancel: &CancellationToken,
+        on_progress: &mut dyn FnMut(Progress),
+    ) -> Result<QuineOutput, QuineError> {
+        let escape_strategy = formatter.options().escape_strategy;
+        let mut report = |phase, bytes_escaped| {
+            if cancel.is_cancelled() {
+                return Err(QuineError::Cancelled);
+            }
+            on_progress(Progress {
+                phase,
+                bytes_escaped,
+                total_bytes: input.len(),
+            });
+            Ok(())
+        };
+
+        // Escape the input chunk by chunk; escaping works per character
+        let mut escaped = String::with_capacity(input.len() * 2);
+        let mut start = 0;
+        while start < input.len() {
+            let mut end = (start + Self::PROGRESS_CHUNK_SIZE).min(input.len());
+            while !input.is_char_boundary(end) {
+                end += 1;
+            }
+            escaped.push_str(&escape_strategy.escape(&input[start..end]));
+            start = end;
+            report(Phase::Escaping, start)?;
+        }
 
         // Generate both formats
-        let one_line = formatter.format_one_line(input, &escaped);
-        let multi_line = formatter.format_multi_line(input, &escaped);
+        report(Phase::OneLine, input.len())?;
+        let one_line = formatter.format_one_line(input, &escaped)?;
+        report(Phase::MultiLine, input.len())?;
+        let multi_line = formatter.format_multi_line(input, &escaped)?;
+        report(Phase::Done, input.len())?;
 
         // Calculate stats
         let stats = QuineStats {
@@ -123,7 +255,7 @@ impl QuineGenerator {
             original: input.to_string(),
             one_line,
             multi_line,
-            escape_strategy: self.options.escape_strategy,
+            escape_strategy,
             stats,
         })
     }
@@ -165,11 +297,6 @@ impl QuineGeneratorBuilder {
         self
     }
 
-    pub fn max_line_length(mut self, length: usize) -> Self {
-        self.options.max_line_length = length;
-        self
-    }
-
     pub fn max_input_size(mut self, size: usize) -> Self {
         self.max_input_size = size;
         self
@@ -180,6 +307,26 @@ impl QuineGeneratorBuilder {
         self
     }
 
+    pub fn header(mut self, header: HeaderOptions) -> Self {
+        self.options.header = header;
+        self
+    }
+
+    pub fn style(mut self, style: StyleProfile) -> Self {
+        self.options.style = style;
+        self
+    }
+
+    pub fn variant_seed(mut self, seed: u64) -> Self {
+        self.options.variant_seed = Some(seed);
+        self
+    }
+
+    pub fn language(mut self, language: Language) -> Self {
+        self.options.language = language;
+        self
+    }
+
     pub fn build(self) -> QuineGenerator {
         QuineGenerator {
             options: self.options,
@@ -215,6 +362,7 @@ mod tests {
         let generator = QuineGenerator::new();
         let result = generator.generate("");
         assert!(matches!(result, Err(QuineError::EmptyInput)));
+        assert_eq!(result.unwrap_err().kind(), "empty_input");
     }
 
     #[test]
@@ -235,4 +383,39 @@ mod tests {
         assert!(output.one_line.contains("\\n") || output.multi_line.contains("\\n"));
     }
 
+    #[test]
+    fn test_source_ends_with_one_newline() {
+        let output = QuineGenerator::new().generate("hello").unwrap();
+        let one_line = output.source(OutputFormat::OneLine);
+        assert_eq!(one_line, format!("{}\n", output.one_line));
+        assert!(output.source(OutputFormat::Both).starts_with(&output.multi_line));
+        assert!(!output.source(OutputFormat::MultiLine).ends_with("\n\n"));
+    }
+
+    #[test]
+    fn test_progress_and_cancellation() {
+        // Multi-byte characters straddle the chunk boundaries
+        let input = "€".repeat(QuineGenerator::PROGRESS_CHUNK_SIZE);
+        let generator = QuineGenerator::new();
+
+        let mut reports = Vec::new();
+        let output = generator
+            .generate_with_progress(&input, &CancellationToken::new(), |p| reports.push(p))
+            .unwrap();
+        assert_eq!(output.one_line, generator.generate(&input).unwrap().one_line);
+        let escaping: Vec<usize> = reports
+            .iter()
+            .filter(|p| p.phase == Phase::Escaping)
+            .map(|p| p.bytes_escaped)
+            .collect();
+        assert_eq!(escaping.len(), 3);
+        assert!(escaping.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(escaping.last(), Some(&input.len()));
+        assert_eq!(reports.last().unwrap().phase, Phase::Done);
+
+        let cancel = CancellationToken::new();
+        let result = generator.generate_with_progress(&input, &cancel, |_| cancel.cancel());
+        assert!(matches!(result, Err(QuineError::Cancelled)));
+    }
+
 }
@@ -0,0 +1,178 @@
+//! Code-golf optimization
+//!
+//! Searches escape strategies and template variants for the shortest
+//! one-line quine of a payload, verifying every candidate it keeps.
+
+use serde::{Deserialize, Serialize};
+
+use crate::escape::EscapeStrategy;
+use crate::formatter::{FilledTemplate, Formatter};
+use crate::generator::{QuineError, QuineGenerator, QuineOutput};
+use crate::variant::TemplateVariant;
+
+const ESCAPE_STRATEGIES: &[EscapeStrategy] = &[
+    EscapeStrategy::Standard,
+    EscapeStrategy::Unicode,
+    EscapeStrategy::Hexadecimal,
+    EscapeStrategy::Raw,
+];
+
+/// Result of a golf search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GolfResult {
+    /// The shortest quine found, generated with the winning settings
+    pub output: QuineOutput,
+    /// Size of the winning one-line quine in bytes
+    pub size: usize,
+    /// Size of the one-line quine with the generator's own settings
+    pub baseline_size: usize,
+    /// Number of candidates evaluated
+    pub candidates: usize,
+}
+
+impl QuineGenerator {
+    /// Search for the shortest verified one-line quine of the input
+    ///
+    /// The payload's contribution to the size depends only on the escape
+    /// strategy and the template's only on the variant, so the two are
+    /// searched one after the other rather than as a full product. Ties
+    /// keep the earlier candidate, so the result is deterministic.
+    pub fn golf(&self, input: &str) -> Result<GolfResult, QuineError> {
+        self.validate(input)?;
+
+        let options = self.options().clone();
+        let baseline = Formatter::new(options.clone());
+        let baseline_size = baseline
+            .fill_one_line(input, &options.escape_strategy.escape(input))?
+            .source
+            .len();
+
+        let mut candidates = 0;
+
+        // Stage 1: escape strategy, with the canonical template
+        let mut best_strategy = options.escape_strategy;
+        let mut best_size = usize::MAX;
+        for strategy in ESCAPE_STRATEGIES {
+            let mut candidate_options = options.clone();
+            candidate_options.escape_strategy = *strategy;
+            let formatter = Formatter::with_variant(candidate_options, TemplateVariant::default());
+            let filled = formatter.fill_one_line(input, &strategy.escape(input))?;
+            candidates += 1;
+            if let Some(size) = verified_size(&filled) {
+                if size < best_size {
+                    best_size = size;
+                    best_strategy = *strategy;
+                }
+            }
+        }
+
+        // Stage 2: template variant, with the winning strategy
+        let mut golf_options = options;
+        golf_options.escape_strategy = best_strategy;
+        let escaped = best_strategy.escape(input);
+        let mut best_variant = TemplateVariant::default();
+        for variant in TemplateVariant::golf_candidates() {
+            let formatter = Formatter::with_variant(golf_options.clone(), variant.clone());
+            let filled = formatter.fill_one_line(input, &escaped)?;
+            candidates += 1;
+            if let Some(size) = verified_size(&filled) {
+                if size < best_size {
+                    best_size = size;
+                    best_variant = variant;
+                }
+            }
+        }
+
+        if best_size == usize::MAX {
+            return Err(QuineError::FormattingError(
+                "no golf candidate reproduced itself".to_string(),
+            ));
+        }
+
+        let formatter = Formatter::with_variant(golf_options, best_variant);
+        let output = self.generate_with(input, &formatter)?;
+
+        Ok(GolfResult {
+            size: output.stats.one_line_bytes,
+            output,
+            baseline_size,
+            candidates,
+        })
+    }
+}
+
+/// Size of a candidate, if it passes verification
+fn verified_size(filled: &FilledTemplate) -> Option<usize> {
+    filled.verify().then_some(filled.source.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formatter::FormatOptions;
+
+    #[test]
+    fn test_golf_beats_default() {
+        let generator = QuineGenerator::new();
+        let result = generator.golf("golf me").unwrap();
+        let default = generator.generate("golf me").unwrap();
+
+        assert_eq!(result.baseline_size, default.stats.one_line_bytes);
+        assert!(result.size < result.baseline_size);
+        assert_eq!(result.size, result.output.one_line.len());
+        assert_eq!(result.output.original, "golf me");
+        assert!(result.candidates > ESCAPE_STRATEGIES.len());
+    }
+
+    #[test]
+    fn test_golf_prefers_shorter_escaping() {
+        // Unicode escaping is never shorter than standard escaping
+        let options = FormatOptions::default().with_escape_strategy(EscapeStrategy::Unicode);
+        let result = QuineGenerator::with_options(options).golf("a\nb").unwrap();
+        assert_eq!(result.output.escape_strategy, EscapeStrategy::Standard);
+    }
+
+    #[test]
+    fn test_golf_result_runs_as_a_module() {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+
+        let result = QuineGenerator::new().golf("golf me").unwrap();
+        let source = &result.output.one_line;
+        assert!(crate::verify::verify(source).unwrap().reproduces);
+
+        // Strict-mode module code rejects assignments to undeclared globals
+        let Ok(mut node) = Command::new("node")
+            .args(["--input-type=module", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+        else {
+            return; // no interpreter to run
+        };
+        node.stdin
+            .take()
+            .unwrap()
+            .write_all(source.as_bytes())
+            .unwrap();
+        let output = node.wait_with_output().unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            format!("{}\n", source)
+        );
+    }
+
+    #[test]
+    fn test_golf_validates_input() {
+        assert!(matches!(
+            QuineGenerator::new().golf(""),
+            Err(QuineError::EmptyInput)
+        ));
+    }
+}
@@ -0,0 +1,199 @@
+//! Header comment generation
+//!
+//! Builds the license/provenance comment emitted at the top of a quine.
+//! The header is part of the quine's own source, so it is reproduced
+//! verbatim when the generated program runs.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::formatter::{DATA_HOLE, OFFSET_HOLE};
+use crate::generator::QuineError;
+
+/// Contents of the header comment emitted above the quine
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeaderOptions {
+    /// License text (may span several lines), e.g. "SPDX-License-Identifier: MIT"
+    pub license: Option<String>,
+    /// Whether to record the PsychoQuine version that generated the quine
+    pub include_version: bool,
+    /// Timestamp to record; supplied by the caller so generation stays deterministic
+    pub timestamp: Option<String>,
+    /// Whether to record the SHA-256 digest of the payload
+    pub include_payload_hash: bool,
+}
+
+impl HeaderOptions {
+    /// Create an empty header
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the license text
+    pub fn with_license<S: Into<String>>(mut self, license: S) -> Self {
+        self.license = Some(license.into());
+        self
+    }
+
+    /// Record the generator version
+    pub fn with_version(mut self) -> Self {
+        self.include_version = true;
+        self
+    }
+
+    /// Record a timestamp
+    pub fn with_timestamp<S: Into<String>>(mut self, timestamp: S) -> Self {
+        self.timestamp = Some(timestamp.into());
+        self
+    }
+
+    /// Record the payload hash
+    pub fn with_payload_hash(mut self) -> Self {
+        self.include_payload_hash = true;
+        self
+    }
+
+    /// Whether the header produces no output
+    pub fn is_empty(&self) -> bool {
+        self.license.is_none()
+            && !self.include_version
+            && self.timestamp.is_none()
+            && !self.include_payload_hash
+    }
+
+    /// Collect the header lines for the given payload
+    pub(crate) fn lines(&self, payload: &str) -> Result<Vec<String>, QuineError> {
+        let mut lines = Vec::new();
+
+        if let Some(license) = &self.license {
+            lines.extend(license.lines().map(|line| line.trim_end().to_string()));
+        }
+        if self.include_version {
+            lines.push(format!(
+                "Generated by PsychoQuine v{}",
+                env!("CARGO_PKG_VERSION")
+            ));
+        }
+        if let Some(timestamp) = &self.timestamp {
+            lines.push(format!("Generated at: {}", timestamp));
+        }
+        if self.include_payload_hash {
+            lines.push(format!("Payload SHA-256: {}", payload_hash(payload)));
+        }
+
+        // The header lives inside a block comment, which must not be closed early
+        if lines.iter().any(|line| line.contains("*/")) {
+            return Err(QuineError::FormattingError(
+                "header text must not contain \"*/\"".to_string(),
+            ));
+        }
+        // The template marks its holes with these, and header text is not escaped
+        if lines
+            .iter()
+            .any(|line| line.contains([DATA_HOLE, OFFSET_HOLE]))
+        {
+            return Err(QuineError::FormattingError(
+                "header text must not contain U+0000 or U+0001 characters".to_string(),
+            ));
+        }
+
+        Ok(lines)
+    }
+
+    /// Render the header as a single-line block comment
+    pub(crate) fn render_one_line(&self, payload: &str) -> Result<String, QuineError> {
+        let lines = self.lines(payload)?;
+        if lines.is_empty() {
+            return Ok(String::new());
+        }
+        let text: Vec<&str> = lines
+            .iter()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .collect();
+        Ok(format!("/* {} */", text.join(" | ")))
+    }
+
+    /// Render the header as a multi-line block comment, ending with a newline
+    pub(crate) fn render_multi_line(&self, payload: &str) -> Result<String, QuineError> {
+        let lines = self.lines(payload)?;
+        if lines.is_empty() {
+            return Ok(String::new());
+        }
+        let mut output = String::from("/*\n");
+        for line in lines {
+            if line.is_empty() {
+                output.push_str(" *\n");
+            } else {
+                output.push_str(" * ");
+                output.push_str(&line);
+                output.push('\n');
+            }
+        }
+        output.push_str(" */\n");
+        Ok(output)
+    }
+}
+
+/// Hex-encoded SHA-256 digest of the payload
+pub fn payload_hash(payload: &str) -> String {
+    Sha256::digest(payload.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_header() {
+        let header = HeaderOptions::new();
+        assert!(header.is_empty());
+        assert_eq!(header.render_one_line("x").unwrap(), "");
+        assert_eq!(header.render_multi_line("x").unwrap(), "");
+    }
+
+    #[test]
+    fn test_header_lines() {
+        let header = HeaderOptions::new()
+            .with_license("MIT License\n\nCopyright (c) 2026")
+            .with_timestamp("2026-01-01T00:00:00Z")
+            .with_payload_hash();
+        let multi = header.render_multi_line("abc").unwrap();
+        assert!(multi.starts_with("/*\n * MIT License\n *\n * Copyright (c) 2026\n"));
+        assert!(multi.contains(" * Generated at: 2026-01-01T00:00:00Z\n"));
+        assert!(multi.contains(
+            "Payload SHA-256: ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        ));
+        assert!(multi.ends_with(" */\n"));
+
+        let one = header.render_one_line("abc").unwrap();
+        assert!(one.starts_with("/* MIT License | Copyright (c) 2026 | Generated at:"));
+        assert!(!one.contains('\n'));
+    }
+
+    #[test]
+    fn test_header_rejects_comment_terminator() {
+        let header = HeaderOptions::new().with_license("evil */ code");
+        assert!(matches!(
+            header.render_one_line("x"),
+            Err(QuineError::FormattingError(_))
+        ));
+    }
+
+    #[test]
+    fn test_header_rejects_template_holes() {
+        for license in ["nul \0 here", "offset \u{1} here"] {
+            let header = HeaderOptions::new().with_license(license);
+            assert!(matches!(
+                header.render_multi_line("x"),
+                Err(QuineError::FormattingError(message)) if message.contains("U+0001")
+            ));
+        }
+        let header = HeaderOptions::new().with_timestamp("2026\u{1}");
+        assert!(header.render_one_line("x").is_err());
+    }
+}
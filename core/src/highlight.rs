@@ -0,0 +1,253 @@
+//! Syntax highlighting for generated quines
+//!
+//! Renders quine source as ANSI-colored text for terminals or as a
+//! standalone HTML document, marking the embedded payload separately
+//! from the template code around it.
+
+use serde::{Deserialize, Serialize};
+
+use crate::formatter::OutputFormat;
+use crate::generator::QuineOutput;
+use crate::lexer::{string_body, tokenize, TokenKind};
+
+/// Target format for highlighted output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum HighlightFormat {
+    /// ANSI escape codes for terminals
+    #[default]
+    Ansi,
+    /// Standalone HTML document with CSS classes
+    Html,
+}
+
+/// Highlighting class of a span of quine source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Class {
+    /// Template code, classified by the lexer
+    Template(TokenKind),
+    /// String literal carrying the embedded payload
+    Payload,
+}
+
+impl Class {
+    fn ansi_code(&self) -> Option<&'static str> {
+        match self {
+            Class::Template(TokenKind::Comment) => Some("\x1b[2m"),
+            Class::Template(TokenKind::Keyword) => Some("\x1b[31m"),
+            Class::Template(TokenKind::Number) => Some("\x1b[33m"),
+            Class::Template(TokenKind::String) => Some("\x1b[90m"),
+            Class::Payload => Some("\x1b[1;97m"),
+            Class::Template(_) => None,
+        }
+    }
+
+    fn css_class(&self) -> Option<&'static str> {
+        match self {
+            Class::Template(TokenKind::Comment) => Some("pq-comment"),
+            Class::Template(TokenKind::Keyword) => Some("pq-keyword"),
+            Class::Template(TokenKind::Identifier) => Some("pq-ident"),
+            Class::Template(TokenKind::Number) => Some("pq-number"),
+            Class::Template(TokenKind::String) => Some("pq-string"),
+            Class::Template(TokenKind::Punctuation) => Some("pq-punct"),
+            Class::Template(TokenKind::Whitespace) => None,
+            Class::Payload => Some("pq-payload"),
+        }
+    }
+}
+
+const HTML_STYLE: &str = "\
+body{margin:0;background:#000;color:#d0d0d0}
+.pq-quine{margin:0;padding:1em;font-family:'JetBrains Mono','Fira Code','SF Mono',monospace;white-space:pre-wrap;word-break:break-all}
+.pq-label{color:#808080}
+.pq-comment{color:#606060;font-style:italic}
+.pq-keyword{color:#b00000}
+.pq-number{color:#d0a000}
+.pq-string{color:#707070}
+.pq-payload{color:#ffffff;font-weight:bold}
+.pq-punct{color:#a0a0a0}";
+
+/// Highlight the selected variant(s) of a quine
+pub fn highlight(output: &QuineOutput, format: HighlightFormat, which: OutputFormat) -> String {
+    let payload = PayloadLiterals::new(output);
+    let sections: Vec<(&str, &str)> = match which {
+        OutputFormat::OneLine => vec![("ONE-LINE QUINE", output.one_line.as_str())],
+        OutputFormat::MultiLine => vec![("MULTI-LINE QUINE", output.multi_line.as_str())],
+        OutputFormat::Both => vec![
+            ("ONE-LINE QUINE", output.one_line.as_str()),
+            ("MULTI-LINE QUINE", output.multi_line.as_str()),
+        ],
+    };
+    let labelled = sections.len() > 1;
+
+    match format {
+        HighlightFormat::Ansi => {
+            let rendered: Vec<String> = sections
+                .iter()
+                .map(|(label, source)| {
+                    let body = render_ansi(source, &payload);
+                    if labelled {
+                        format!("\x1b[2m═══ {} ═══\x1b[0m\n{}", label, body)
+                    } else {
+                        body
+                    }
+                })
+                .collect();
+            rendered.join("\n")
+        }
+        HighlightFormat::Html => {
+            let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+            html.push_str("<title>PsychoQuine</title>\n<style>\n");
+            html.push_str(HTML_STYLE);
+            html.push_str("\n</style>\n</head>\n<body>\n");
+            for (label, source) in sections {
+                if labelled {
+                    html.push_str(&format!("<pre class=\"pq-quine pq-label\">{}</pre>\n", label));
+                }
+                html.push_str("<pre class=\"pq-quine\"><code>");
+                html.push_str(&render_html(source, &payload));
+                html.push_str("</code></pre>\n");
+            }
+            html.push_str("</body>\n</html>\n");
+            html
+        }
+    }
+}
+
+fn render_ansi(source: &str, payload: &PayloadLiterals) -> String {
+    let mut output = String::with_capacity(source.len() * 2);
+    for (class, text) in classify(source, payload) {
+        match class.ansi_code() {
+            Some(code) => {
+                output.push_str(code);
+                output.push_str(text);
+                output.push_str("\x1b[0m");
+            }
+            None => output.push_str(text),
+        }
+    }
+    output
+}
+
+fn render_html(source: &str, payload: &PayloadLiterals) -> String {
+    let mut output = String::with_capacity(source.len() * 2);
+    for (class, text) in classify(source, payload) {
+        match class.css_class() {
+            Some(class) => {
+                output.push_str(&format!("<span class=\"{}\">", class));
+                output.push_str(&escape_html(text));
+                output.push_str("</span>");
+            }
+            None => output.push_str(&escape_html(text)),
+        }
+    }
+    output
+}
+
+fn escape_html(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            c => output.push(c),
+        }
+    }
+    output
+}
+
+/// Escaped payload bodies for each quote style a template may use
+struct PayloadLiterals {
+    double: String,
+    single: String,
+}
+
+impl PayloadLiterals {
+    fn new(output: &QuineOutput) -> Self {
+        Self {
+            double: output.escape_strategy.escape_quoted(&output.original, '"'),
+            single: output.escape_strategy.escape_quoted(&output.original, '\''),
+        }
+    }
+
+    fn matches(&self, quote: char, body: &str) -> bool {
+        match quote {
+            '"' => body == self.double,
+            '\'' => body == self.single,
+            _ => false,
+        }
+    }
+}
+
+/// Classify the spans of quine source for highlighting
+///
+/// A string literal whose body equals the escaped payload is reported as
+/// `Payload`; every other span belongs to the template.
+fn classify<'a>(source: &'a str, payload: &PayloadLiterals) -> Vec<(Class, &'a str)> {
+    tokenize(source)
+        .into_iter()
+        .map(|(kind, text)| {
+            let is_payload = kind == TokenKind::String
+                && string_body(text).is_some_and(|(quote, body)| payload.matches(quote, body));
+            if is_payload {
+                (Class::Payload, text)
+            } else {
+                (Class::Template(kind), text)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::escape::EscapeStrategy;
+    use crate::formatter::FormatOptions;
+    use crate::generator::QuineGenerator;
+    use crate::style::StyleProfile;
+
+    #[test]
+    fn test_classify_marks_payload() {
+        let output = QuineGenerator::new().generate("say \"hi\"").unwrap();
+        let tokens = classify(&output.one_line, &PayloadLiterals::new(&output));
+
+        let payloads: Vec<&str> = tokens
+            .iter()
+            .filter(|(class, _)| *class == Class::Payload)
+            .map(|(_, text)| *text)
+            .collect();
+        assert_eq!(payloads, vec![r#""say \"hi\"""#]);
+
+        let rebuilt: String = tokens.iter().map(|(_, text)| *text).collect();
+        assert_eq!(rebuilt, output.one_line);
+    }
+
+    #[test]
+    fn test_ansi_highlight() {
+        let output = QuineGenerator::new().generate("payload").unwrap();
+        let ansi = highlight(&output, HighlightFormat::Ansi, OutputFormat::OneLine);
+        assert!(ansi.contains("\x1b[1;97m\"payload\"\x1b[0m"));
+        assert!(ansi.contains("\x1b[31mfunction\x1b[0m"));
+    }
+
+    #[test]
+    fn test_single_quoted_payload() {
+        let options = FormatOptions::default()
+            .with_escape_strategy(EscapeStrategy::Unicode)
+            .with_style(StyleProfile::preset("single-quote").unwrap());
+        let output = QuineGenerator::with_options(options).generate("it's").unwrap();
+        let ansi = highlight(&output, HighlightFormat::Ansi, OutputFormat::MultiLine);
+        assert!(ansi.contains("\x1b[1;97m'it\\'s'\x1b[0m"));
+    }
+
+    #[test]
+    fn test_html_highlight() {
+        let output = QuineGenerator::new().generate("<b>&</b>").unwrap();
+        let html = highlight(&output, HighlightFormat::Html, OutputFormat::Both);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<span class=\"pq-payload\">&quot;&lt;b&gt;&amp;&lt;/b&gt;&quot;</span>"));
+        assert!(html.contains("MULTI-LINE QUINE"));
+        assert!(!html.contains("<b>"));
+    }
+}
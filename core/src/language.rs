@@ -0,0 +1,82 @@
+//! Target languages for generated quines
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::request::ParseOptionError;
+
+/// Language a quine is generated in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Language {
+    /// JavaScript, runnable with Node.js or in a browser console
+    #[default]
+    JavaScript,
+}
+
+impl Language {
+    /// All registered languages
+    pub const ALL: &'static [Language] = &[Language::JavaScript];
+
+    /// Canonical lowercase name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::JavaScript => "javascript",
+        }
+    }
+
+    /// File extension of source files, without the dot
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Language::JavaScript => "js",
+        }
+    }
+
+    /// Command (program and arguments) that runs a program read from stdin
+    pub fn interpreter(&self) -> &'static [&'static str] {
+        match self {
+            Language::JavaScript => &["node", "-"],
+        }
+    }
+
+    /// Look up a language by name or common alias
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "javascript" | "js" | "node" => Some(Language::JavaScript),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Language {
+    type Err = ParseOptionError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::from_name(name).ok_or_else(|| {
+            let names = Self::ALL.iter().map(|language| language.name()).collect();
+            ParseOptionError::new("language", name, names)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_names() {
+        for language in Language::ALL {
+            assert_eq!(Language::from_name(language.name()), Some(*language));
+        }
+        assert_eq!(Language::from_name("JS"), Some(Language::JavaScript));
+        assert_eq!(Language::from_name("cobol"), None);
+        assert_eq!(Language::JavaScript.extension(), "js");
+    }
+}
@@ -0,0 +1,109 @@
+//! Minimal JavaScript lexer
+//!
+//! Splits quine source into classified spans. It only needs to understand
+//! the constructs PsychoQuine templates use: comments, string literals,
+//! numbers, identifiers and punctuation.
+
+/// Lexical category of a span of source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    Comment,
+    Keyword,
+    Identifier,
+    Number,
+    String,
+    Punctuation,
+    Whitespace,
+}
+
+const KEYWORDS: &[&str] = &[
+    "function", "var", "let", "const", "return", "new", "typeof", "this", "true", "false", "null",
+];
+
+/// Split source into classified spans that concatenate back to the input
+pub(crate) fn tokenize(source: &str) -> Vec<(TokenKind, &str)> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+
+    while let Some(ch) = rest.chars().next() {
+        let (kind, len) = if rest.starts_with("/*") {
+            let len = rest.find("*/").map(|end| end + 2).unwrap_or(rest.len());
+            (TokenKind::Comment, len)
+        } else if rest.starts_with("//") {
+            (TokenKind::Comment, rest.find('\n').unwrap_or(rest.len()))
+        } else if ch == '"' || ch == '\'' || ch == '`' {
+            (TokenKind::String, string_literal_len(rest, ch))
+        } else if ch.is_ascii_digit() {
+            (TokenKind::Number, span_len(rest, |c| c.is_ascii_alphanumeric() || c == '.'))
+        } else if ch.is_alphabetic() || ch == '_' || ch == '$' {
+            let len = span_len(rest, |c| c.is_alphanumeric() || c == '_' || c == '$');
+            if KEYWORDS.contains(&&rest[..len]) {
+                (TokenKind::Keyword, len)
+            } else {
+                (TokenKind::Identifier, len)
+            }
+        } else if ch.is_whitespace() {
+            (TokenKind::Whitespace, span_len(rest, char::is_whitespace))
+        } else {
+            (TokenKind::Punctuation, ch.len_utf8())
+        };
+
+        tokens.push((kind, &rest[..len]));
+        rest = &rest[len..];
+    }
+
+    tokens
+}
+
+/// Quote character and body of a terminated string literal token
+pub(crate) fn string_body(literal: &str) -> Option<(char, &str)> {
+    let quote = literal.chars().next()?;
+    if literal.len() < 2 {
+        return None;
+    }
+    let body = literal.strip_prefix(quote)?.strip_suffix(quote)?;
+    Some((quote, body))
+}
+
+/// Length of the leading run of characters matching `pred`
+fn span_len(text: &str, pred: impl Fn(char) -> bool) -> usize {
+    text.find(|c: char| !pred(c)).unwrap_or(text.len())
+}
+
+/// Length of the string literal at the start of `text`, including quotes
+fn string_literal_len(text: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, ch) in text.char_indices().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if ch == '\\' {
+            escaped = true;
+        } else if ch == quote {
+            return i + ch.len_utf8();
+        }
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_round_trip() {
+        let source = r#"/* c */ var d="a\"b";console.log(d.slice(0,12))"#;
+        let tokens = tokenize(source);
+        let rebuilt: String = tokens.iter().map(|(_, text)| *text).collect();
+        assert_eq!(rebuilt, source);
+        assert_eq!(tokens[0], (TokenKind::Comment, "/* c */"));
+        assert!(tokens.contains(&(TokenKind::String, r#""a\"b""#)));
+        assert!(tokens.contains(&(TokenKind::Number, "12")));
+    }
+
+    #[test]
+    fn test_string_body() {
+        assert_eq!(string_body("'x'"), Some(('\'', "x")));
+        assert_eq!(string_body("\"unterminated"), None);
+        assert_eq!(string_body("\""), None);
+    }
+}
@@ -3,13 +3,42 @@
 //! A resource-agnostic quine generation engine capable of transforming
 //! arbitrary textual resources into self-replicating program structures.
 
+mod config;
 mod escape;
+#[cfg(feature = "ffi")]
+mod ffi;
 mod formatter;
 mod generator;
+mod golf;
+mod header;
+mod highlight;
+mod language;
+mod lexer;
+mod progress;
+mod request;
+mod style;
+mod templates;
+mod variant;
+mod verify;
+#[cfg(feature = "wasm")]
+mod wasm;
 
+pub use config::{Config, Indent, CONFIG_FILE_NAME};
 pub use escape::EscapeStrategy;
 pub use formatter::{FormatOptions, OutputFormat};
-pub use generator::{QuineGenerator, QuineGeneratorBuilder, QuineOutput, QuineError};
+pub use header::{payload_hash, HeaderOptions};
+pub use golf::GolfResult;
+pub use highlight::{highlight, HighlightFormat};
+pub use language::Language;
+pub use progress::{CancellationToken, Phase, Progress};
+pub use request::{GenerateRequest, ParseOptionError};
+pub use style::{BraceStyle, QuoteStyle, StyleProfile};
+pub use templates::{EscapeStrategyInfo, LanguageInfo, Templates};
+pub use generator::{QuineGenerator, QuineGeneratorBuilder, QuineOutput, QuineError, QuineStats};
+pub use verify::{
+    analyze, extract, verify, verify_with_interpreter, verify_with_interpreter_timeout,
+    QuineAnalysis, VerifyMethod, VerifyReport, INTERPRETER_TIMEOUT,
+};
 
 /// Re-export of the main generation function for convenience
 pub fn generate<S: AsRef<str>>(input: S) -> Result<QuineOutput, QuineError> {
@@ -0,0 +1,74 @@
+//! Progress reporting and cancellation for long generations
+//!
+//! `QuineGenerator::generate_with_progress` reports a `Progress` after each
+//! step and checks its `CancellationToken` in between, so a front end can
+//! show how far a large input has got and stop it early. Escaping reports
+//! per chunk; building the one-line and multi-line quines reports once
+//! each, so cancellation waits for the quine being built.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+/// Shared flag asking a running generation to stop
+///
+/// Clones share the flag, so one clone can be handed to the generation and
+/// another kept to cancel it from a different thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Create a token that is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the generation to stop at its next check
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether `cancel` has been called on this token or a clone of it
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Step of a generation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Phase {
+    /// Escaping the input, reported after each chunk
+    Escaping,
+    /// Building the one-line quine
+    OneLine,
+    /// Building the multi-line quine
+    MultiLine,
+    /// Generation finished
+    Done,
+}
+
+/// How far a generation has got
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Progress {
+    /// Current step
+    pub phase: Phase,
+    /// Input bytes escaped so far
+    pub bytes_escaped: usize,
+    /// Size of the input in bytes
+    pub total_bytes: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clones_share_cancellation() {
+        let token = CancellationToken::new();
+        let handle = token.clone();
+        assert!(!token.is_cancelled());
+        handle.cancel();
+        assert!(token.is_cancelled());
+    }
+}
@@ -0,0 +1,225 @@
+//! Generation requests shared by every front end
+//!
+//! `GenerateRequest` is the serializable form of the generation options.
+//! The CLI, `psychoquine.toml` and the desktop app all resolve options
+//! through it, and option values are parsed with the `FromStr`
+//! implementations of the option types, so each front end accepts exactly
+//! the same names and aliases.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Indent;
+use crate::formatter::{is_valid_indent, FormatOptions};
+use crate::generator::{QuineError, QuineGenerator};
+use crate::style::StyleProfile;
+
+/// An option value outside the option's vocabulary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptionError {
+    /// Name of the option, e.g. `escape_strategy`
+    pub option: &'static str,
+    /// The value given
+    pub value: String,
+    /// Canonical names the option accepts
+    pub expected: Vec<&'static str>,
+}
+
+impl ParseOptionError {
+    pub(crate) fn new(option: &'static str, value: &str, expected: Vec<&'static str>) -> Self {
+        Self {
+            option,
+            value: value.to_string(),
+            expected,
+        }
+    }
+}
+
+impl fmt::Display for ParseOptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid value '{}' for {} (expected one of: {})",
+            self.value,
+            self.option,
+            self.expected.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for ParseOptionError {}
+
+impl From<ParseOptionError> for QuineError {
+    fn from(error: ParseOptionError) -> Self {
+        QuineError::InvalidConfig(error.to_string())
+    }
+}
+
+/// Options for one generation, as the front ends receive them
+///
+/// Every field is optional; unset ones keep the built-in default.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GenerateRequest {
+    /// Escape strategy name (`standard`, `unicode`, `hex`, `raw`)
+    pub escape_strategy: Option<String>,
+    /// Indentation for multi-line output
+    pub indent: Option<Indent>,
+    /// Output format name (`one-line`, `multi-line`, `both`)
+    pub output_format: Option<String>,
+    /// Target language name
+    pub language: Option<String>,
+    /// Style preset name for multi-line output
+    pub style: Option<String>,
+    /// Seed selecting an equivalent template spelling
+    pub seed: Option<u64>,
+    /// Maximum input size in bytes
+    pub max_input_size: Option<usize>,
+}
+
+impl GenerateRequest {
+    /// Apply the request over `options`
+    pub fn apply(&self, options: &mut FormatOptions) -> Result<(), ParseOptionError> {
+        if let Some(name) = &self.escape_strategy {
+            options.escape_strategy = name.parse()?;
+        }
+        match &self.indent {
+            Some(Indent::Spaces(count)) => options.indent = " ".repeat(*count),
+            Some(Indent::Text(text)) if is_valid_indent(text) => options.indent = text.clone(),
+            Some(Indent::Text(text)) => {
+                return Err(ParseOptionError::new("indent", text, vec!["spaces", "tabs"]))
+            }
+            None => {}
+        }
+        if let Some(name) = &self.output_format {
+            options.output_format = name.parse()?;
+        }
+        if let Some(name) = &self.language {
+            options.language = name.parse()?;
+        }
+        if let Some(name) = &self.style {
+            options.style = StyleProfile::preset(name).ok_or_else(|| {
+                ParseOptionError::new("style", name, StyleProfile::PRESETS.to_vec())
+            })?;
+        }
+        if let Some(seed) = self.seed {
+            options.variant_seed = Some(seed);
+        }
+        Ok(())
+    }
+
+    /// Format options with the request applied over the defaults
+    pub fn format_options(&self) -> Result<FormatOptions, ParseOptionError> {
+        let mut options = FormatOptions::default();
+        self.apply(&mut options)?;
+        Ok(options)
+    }
+
+    /// A generator for the request
+    pub fn generator(&self) -> Result<QuineGenerator, ParseOptionError> {
+        let generator = QuineGenerator::with_options(self.format_options()?);
+        Ok(match self.max_input_size {
+            Some(size) => generator.with_max_input_size(size),
+            None => generator,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::escape::EscapeStrategy;
+    use crate::formatter::OutputFormat;
+    use crate::language::Language;
+
+    #[test]
+    fn test_vocabulary_round_trips() {
+        for strategy in EscapeStrategy::ALL {
+            assert_eq!(strategy.to_string().parse(), Ok(*strategy));
+        }
+        for format in OutputFormat::ALL {
+            assert_eq!(format.to_string().parse(), Ok(*format));
+        }
+        for language in Language::ALL {
+            assert_eq!(language.to_string().parse(), Ok(*language));
+        }
+        // Case and aliases are accepted everywhere
+        assert_eq!("Hexadecimal".parse(), Ok(EscapeStrategy::Hexadecimal));
+        assert_eq!("ONE-LINE".parse(), Ok(OutputFormat::OneLine));
+        assert_eq!("node".parse(), Ok(Language::JavaScript));
+    }
+
+    #[test]
+    fn test_request_from_json() {
+        let request: GenerateRequest = serde_json::from_str(
+            r#"{"escape_strategy": "Unicode", "indent": "\t", "style": "allman", "seed": 3}"#,
+        )
+        .unwrap();
+        let options = request.format_options().unwrap();
+        assert_eq!(options.escape_strategy, EscapeStrategy::Unicode);
+        assert_eq!(options.indent, "\t");
+        assert_eq!(options.style, StyleProfile::preset("allman").unwrap());
+        assert_eq!(options.variant_seed, Some(3));
+
+        assert!(serde_json::from_str::<GenerateRequest>(r#"{"escape": "raw"}"#).is_err());
+    }
+
+    #[test]
+    fn test_output_json_is_a_request() {
+        let request = GenerateRequest {
+            escape_strategy: Some("hexadecimal".to_string()),
+            ..GenerateRequest::default()
+        };
+        let output = request.generator().unwrap().generate("hi").unwrap();
+        let json = serde_json::to_value(&output).unwrap();
+        assert_eq!(json["escape_strategy"], "hex");
+
+        let request: GenerateRequest = serde_json::from_value(
+            serde_json::json!({ "escape_strategy": json["escape_strategy"] }),
+        )
+        .unwrap();
+        assert_eq!(
+            request.format_options().unwrap().escape_strategy,
+            EscapeStrategy::Hexadecimal
+        );
+        let strategy: EscapeStrategy = serde_json::from_str(r#""Unicode""#).unwrap();
+        assert_eq!(strategy, EscapeStrategy::Unicode);
+    }
+
+    #[test]
+    fn test_invalid_value() {
+        let request = GenerateRequest {
+            escape_strategy: Some("rot13".to_string()),
+            ..GenerateRequest::default()
+        };
+        let error = request.format_options().unwrap_err();
+        assert_eq!(error.option, "escape_strategy");
+        assert_eq!(error.expected, vec!["standard", "unicode", "hex", "raw"]);
+        assert_eq!(
+            error.to_string(),
+            "invalid value 'rot13' for escape_strategy (expected one of: standard, unicode, hex, raw)"
+        );
+    }
+
+    #[test]
+    fn test_indent_is_only_spaces_and_tabs() {
+        for indent in ["", "  ", "\t", " \t"] {
+            let request = GenerateRequest {
+                indent: Some(Indent::Text(indent.to_string())),
+                ..GenerateRequest::default()
+            };
+            assert_eq!(request.format_options().unwrap().indent, indent);
+        }
+        for indent in ["x", "\u{1} ", "\0", " \n", "\u{a0}"] {
+            let request = GenerateRequest {
+                indent: Some(Indent::Text(indent.to_string())),
+                ..GenerateRequest::default()
+            };
+            let error = request.format_options().unwrap_err();
+            assert_eq!(error.option, "indent", "{:?}", indent);
+            assert_eq!(error.value, indent);
+        }
+    }
+}
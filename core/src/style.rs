@@ -0,0 +1,142 @@
+//! Code style profiles for multi-line output
+//!
+//! Styles are applied while the quine template is built, so the program
+//! reproduces its own styled source rather than a reformatted copy.
+
+use serde::{Deserialize, Serialize};
+
+/// Placement of opening braces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BraceStyle {
+    /// Opening brace on the same line (`function() {`)
+    #[default]
+    KAndR,
+    /// Opening brace on its own line
+    Allman,
+}
+
+/// Quote character used for template string literals
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum QuoteStyle {
+    /// Double quotes (`"..."`)
+    #[default]
+    Double,
+    /// Single quotes (`'...'`)
+    Single,
+}
+
+impl QuoteStyle {
+    /// The quote character
+    pub fn as_char(&self) -> char {
+        match self {
+            QuoteStyle::Double => '"',
+            QuoteStyle::Single => '\'',
+        }
+    }
+}
+
+/// Code style applied to multi-line output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StyleProfile {
+    /// Placement of opening braces
+    pub brace_style: BraceStyle,
+    /// Whether binary operators, assignments and commas are padded with spaces
+    pub spaces_around_operators: bool,
+    /// Quote character for the payload literal
+    pub quote_style: QuoteStyle,
+    /// Whether the last statement of a block and the program itself end with `;`
+    pub trailing_semicolons: bool,
+}
+
+impl Default for StyleProfile {
+    fn default() -> Self {
+        Self::compact()
+    }
+}
+
+impl StyleProfile {
+    /// Names of the built-in presets
+    pub const PRESETS: &'static [&'static str] = &["compact", "standard", "allman", "single-quote"];
+
+    /// Dense K&R layout without operator spacing (the default)
+    pub fn compact() -> Self {
+        Self {
+            brace_style: BraceStyle::KAndR,
+            spaces_around_operators: false,
+            quote_style: QuoteStyle::Double,
+            trailing_semicolons: false,
+        }
+    }
+
+    /// Conventional K&R layout with spaced operators and trailing semicolons
+    pub fn standard() -> Self {
+        Self {
+            spaces_around_operators: true,
+            trailing_semicolons: true,
+            ..Self::compact()
+        }
+    }
+
+    /// Look up a built-in preset by name
+    pub fn preset(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "compact" => Some(Self::compact()),
+            "standard" => Some(Self::standard()),
+            "allman" => Some(Self {
+                brace_style: BraceStyle::Allman,
+                ..Self::standard()
+            }),
+            "single-quote" => Some(Self {
+                quote_style: QuoteStyle::Single,
+                ..Self::standard()
+            }),
+            _ => None,
+        }
+    }
+
+    /// Pad an operator according to the profile
+    pub(crate) fn op(&self, operator: &str) -> String {
+        if self.spaces_around_operators {
+            format!(" {} ", operator)
+        } else {
+            operator.to_string()
+        }
+    }
+
+    /// Separator between call arguments
+    pub(crate) fn comma(&self) -> &'static str {
+        if self.spaces_around_operators {
+            ", "
+        } else {
+            ","
+        }
+    }
+
+    /// Terminator for the last statement of a block
+    pub(crate) fn trailing(&self) -> &'static str {
+        if self.trailing_semicolons {
+            ";"
+        } else {
+            ""
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets() {
+        for name in StyleProfile::PRESETS {
+            assert!(StyleProfile::preset(name).is_some(), "missing preset {}", name);
+        }
+        assert_eq!(StyleProfile::default(), StyleProfile::compact());
+        assert_eq!(
+            StyleProfile::preset("ALLMAN").unwrap().brace_style,
+            BraceStyle::Allman
+        );
+        assert!(StyleProfile::preset("nonexistent").is_none());
+    }
+}
@@ -0,0 +1,59 @@
+//! Catalogue of what quines can be generated with
+//!
+//! Front ends list languages, escape strategies and style presets from
+//! here rather than from their own copies.
+
+use serde::Serialize;
+
+use crate::escape::EscapeStrategy;
+use crate::language::Language;
+use crate::style::StyleProfile;
+
+/// A language quines can be generated in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LanguageInfo {
+    pub name: &'static str,
+    /// File extension, without the dot
+    pub extension: &'static str,
+    /// Command that runs a program read from stdin
+    pub interpreter: &'static [&'static str],
+}
+
+/// An escape strategy and what it does
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EscapeStrategyInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+/// Languages, escape strategies and style presets, by canonical name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Templates {
+    pub languages: Vec<LanguageInfo>,
+    pub escape_strategies: Vec<EscapeStrategyInfo>,
+    pub styles: Vec<&'static str>,
+}
+
+impl Templates {
+    /// Everything registered in this build
+    pub fn all() -> Self {
+        Self {
+            languages: Language::ALL
+                .iter()
+                .map(|language| LanguageInfo {
+                    name: language.name(),
+                    extension: language.extension(),
+                    interpreter: language.interpreter(),
+                })
+                .collect(),
+            escape_strategies: EscapeStrategy::ALL
+                .iter()
+                .map(|strategy| EscapeStrategyInfo {
+                    name: strategy.name(),
+                    description: strategy.description(),
+                })
+                .collect(),
+            styles: StyleProfile::PRESETS.to_vec(),
+        }
+    }
+}
@@ -0,0 +1,305 @@
+//! Template variants
+//!
+//! A variant is one concrete spelling of the quine template: identifier
+//! names, declaration keyword, wrapper function, the trick used to rebuild
+//! the data literal, and statement order. Every variant prints itself
+//! exactly; a seed picks one deterministically.
+
+/// Wrapper around the quine body
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Wrapper {
+    /// `(function(){...})()`
+    Function,
+    /// `(()=>{...})()`
+    Arrow,
+    /// Top-level statements without a wrapper (script scope)
+    Bare,
+}
+
+/// How the program rebuilds its own data literal at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DataCodec {
+    /// `JSON.stringify(d)`
+    Json,
+    /// Quote variable plus `split`/`join` escaping of `\`, `"` and newlines
+    SplitJoin(QuoteSource),
+}
+
+/// Spelling of the quote character for `DataCodec::SplitJoin`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum QuoteSource {
+    /// `String.fromCharCode(34)`
+    FromCharCode,
+    /// `'"'`
+    SingleQuoted,
+    /// `"\""`
+    Escaped,
+}
+
+impl QuoteSource {
+    fn expression(&self) -> &'static str {
+        match self {
+            QuoteSource::FromCharCode => "String.fromCharCode(34)",
+            QuoteSource::SingleQuoted => "'\"'",
+            QuoteSource::Escaped => "\"\\\"\"",
+        }
+    }
+}
+
+/// Declarations in the quine body (the print statement always comes last)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Declaration {
+    Data,
+    Payload,
+    Quote,
+}
+
+const IDENTIFIERS: &[&str] = &[
+    "d", "p", "q", "s", "t", "x", "y", "z", "a", "b", "c", "k", "_", "$", "src", "data", "code",
+    "self", "body", "text", "me", "src_",
+];
+const KEYWORDS: &[&str] = &["var", "let", "const"];
+const SLICE_METHODS: &[&str] = &["slice", "substring"];
+const QUOTE_SOURCES: &[QuoteSource] = &[
+    QuoteSource::FromCharCode,
+    QuoteSource::SingleQuoted,
+    QuoteSource::Escaped,
+];
+
+/// One concrete spelling of the quine template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TemplateVariant {
+    pub data_ident: &'static str,
+    pub payload_ident: &'static str,
+    pub quote_ident: &'static str,
+    /// Declaration keyword
+    pub keyword: &'static str,
+    pub wrapper: Wrapper,
+    pub codec: DataCodec,
+    pub slice_method: &'static str,
+    /// Order of the declarations preceding the print statement
+    pub order: Vec<Declaration>,
+}
+
+impl Default for TemplateVariant {
+    /// The canonical spelling used when no seed is given
+    fn default() -> Self {
+        Self {
+            data_ident: "d",
+            payload_ident: "p",
+            quote_ident: "q",
+            keyword: "var",
+            wrapper: Wrapper::Function,
+            codec: DataCodec::Json,
+            slice_method: "slice",
+            order: vec![Declaration::Data, Declaration::Payload],
+        }
+    }
+}
+
+impl TemplateVariant {
+    /// Pick a variant deterministically from a seed
+    pub fn from_seed(seed: u64) -> Self {
+        let mut rng = SplitMix64(seed);
+
+        let mut pool: Vec<&'static str> = IDENTIFIERS.to_vec();
+        let data_ident = pool.remove(rng.below(pool.len()));
+        let payload_ident = pool.remove(rng.below(pool.len()));
+        let quote_ident = pool.remove(rng.below(pool.len()));
+
+        let keyword = KEYWORDS[rng.below(KEYWORDS.len())];
+        let wrapper = if rng.below(2) == 0 {
+            Wrapper::Function
+        } else {
+            Wrapper::Arrow
+        };
+        let codec = match rng.below(QUOTE_SOURCES.len() + 1) {
+            0 => DataCodec::Json,
+            n => DataCodec::SplitJoin(QUOTE_SOURCES[n - 1]),
+        };
+        let slice_method = SLICE_METHODS[rng.below(SLICE_METHODS.len())];
+
+        let mut order = vec![Declaration::Data, Declaration::Payload];
+        if matches!(codec, DataCodec::SplitJoin(_)) {
+            order.push(Declaration::Quote);
+        }
+        // Fisher-Yates shuffle
+        for i in (1..order.len()).rev() {
+            order.swap(i, rng.below(i + 1));
+        }
+
+        Self {
+            data_ident,
+            payload_ident,
+            quote_ident,
+            keyword,
+            wrapper,
+            codec,
+            slice_method,
+            order,
+        }
+    }
+
+    /// Every spelling the golf optimizer considers
+    ///
+    /// Identifiers are fixed to single characters, the shortest possible;
+    /// only choices that can change the size of the output are enumerated.
+    /// Every candidate declares its variables, so it also runs as an ES
+    /// module or in strict mode.
+    pub fn golf_candidates() -> Vec<Self> {
+        let mut candidates = Vec::new();
+        for wrapper in [Wrapper::Function, Wrapper::Arrow, Wrapper::Bare] {
+            for keyword in KEYWORDS.iter().copied() {
+                for codec in std::iter::once(DataCodec::Json)
+                    .chain(QUOTE_SOURCES.iter().map(|source| DataCodec::SplitJoin(*source)))
+                {
+                    for slice_method in SLICE_METHODS {
+                        let mut order = vec![Declaration::Data, Declaration::Payload];
+                        if matches!(codec, DataCodec::SplitJoin(_)) {
+                            order.push(Declaration::Quote);
+                        }
+                        candidates.push(Self {
+                            keyword,
+                            wrapper,
+                            codec,
+                            slice_method,
+                            order,
+                            ..Self::default()
+                        });
+                    }
+                }
+            }
+        }
+        candidates
+    }
+
+    /// The same variant rebuilding its literal with `JSON.stringify`
+    ///
+    /// `SplitJoin` only escapes `\`, `"` and newlines, so sources containing
+    /// other control characters (e.g. a tab indent) fall back to this.
+    pub fn with_json_codec(&self) -> Self {
+        let mut variant = self.clone();
+        variant.codec = DataCodec::Json;
+        variant.order.retain(|decl| *decl != Declaration::Quote);
+        variant
+    }
+
+    /// Whether the codec can encode every character of `data`
+    pub fn supports(&self, data: &str) -> bool {
+        match self.codec {
+            DataCodec::Json => true,
+            DataCodec::SplitJoin(_) => !data.chars().any(|c| c < ' ' && c != '\n'),
+        }
+    }
+
+    /// Encode the data string as the literal the program rebuilds at runtime
+    pub fn encode(&self, data: &str) -> String {
+        match self.codec {
+            DataCodec::Json => crate::escape::json_string_literal(data),
+            DataCodec::SplitJoin(_) => {
+                let mut output = String::with_capacity(data.len() + 2);
+                output.push('"');
+                for ch in data.chars() {
+                    match ch {
+                        '\\' => output.push_str("\\\\"),
+                        '"' => output.push_str("\\\""),
+                        '\n' => output.push_str("\\n"),
+                        c => output.push(c),
+                    }
+                }
+                output.push('"');
+                output
+            }
+        }
+    }
+
+    /// Spell a declaration of `ident` with the variant's keyword
+    pub fn declaration(&self, ident: &str, assign: &str, value: &str) -> String {
+        format!("{} {}{}{}", self.keyword, ident, assign, value)
+    }
+
+    /// Right-hand side of the quote declaration
+    pub fn quote_expression(&self) -> &'static str {
+        match self.codec {
+            DataCodec::Json => QuoteSource::FromCharCode.expression(),
+            DataCodec::SplitJoin(source) => source.expression(),
+        }
+    }
+
+    /// Runtime expression rebuilding the data literal, joined with `plus`
+    pub fn literal_expression(&self, plus: &str) -> String {
+        let d = self.data_ident;
+        let q = self.quote_ident;
+        match self.codec {
+            DataCodec::Json => format!("JSON.stringify({})", d),
+            DataCodec::SplitJoin(_) => format!(
+                r#"{q}{plus}{d}.split("\\").join("\\\\").split({q}).join("\\"{plus}{q}).split("\n").join("\\n"){plus}{q}"#,
+                q = q,
+                d = d,
+                plus = plus,
+            ),
+        }
+    }
+}
+
+/// Small deterministic PRNG so variants are reproducible across platforms
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed_is_deterministic() {
+        assert_eq!(TemplateVariant::from_seed(42), TemplateVariant::from_seed(42));
+    }
+
+    #[test]
+    fn test_seeds_produce_distinct_variants() {
+        let variants: Vec<TemplateVariant> = (0..32).map(TemplateVariant::from_seed).collect();
+        for variant in &variants {
+            assert_ne!(variant.data_ident, variant.payload_ident);
+            assert_ne!(variant.data_ident, variant.quote_ident);
+        }
+        let distinct = variants
+            .iter()
+            .enumerate()
+            .filter(|(i, v)| !variants[..*i].contains(v))
+            .count();
+        assert!(distinct > 28);
+    }
+
+    #[test]
+    fn test_golf_candidates_are_distinct() {
+        let candidates = TemplateVariant::golf_candidates();
+        assert_eq!(candidates.len(), 3 * 3 * 4 * 2);
+        assert!(candidates.contains(&TemplateVariant::default()));
+        assert!(candidates.iter().all(|variant| !variant.keyword.is_empty()));
+    }
+
+    #[test]
+    fn test_split_join_encoding() {
+        let variant = TemplateVariant {
+            codec: DataCodec::SplitJoin(QuoteSource::FromCharCode),
+            ..TemplateVariant::default()
+        };
+        assert_eq!(variant.encode("a\\\"\n"), r#""a\\\"\n""#);
+        assert!(variant.supports("x\ny"));
+        assert!(!variant.supports("x\ty"));
+        assert!(variant.with_json_codec().supports("x\ty"));
+    }
+}
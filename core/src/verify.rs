@@ -0,0 +1,549 @@
+//! Quine verification and inspection
+//!
+//! Recognizes quines produced by PsychoQuine, recovers their payload and
+//! checks that they reproduce themselves, either statically or by running
+//! them with the language's interpreter.
+
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::escape::{unescape_js, EscapeStrategy};
+use crate::formatter::utf16_to_byte_offset;
+use crate::generator::QuineError;
+use crate::language::Language;
+use crate::lexer::{string_body, tokenize, TokenKind};
+use crate::variant::{DataCodec, QuoteSource, TemplateVariant};
+
+/// How a quine was verified, named `static` or `runtime` in JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VerifyMethod {
+    /// Rebuilt the output from the decoded data literal without running it
+    Static,
+    /// Ran the program with the language's interpreter
+    Runtime,
+}
+
+/// Outcome of verifying a quine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyReport {
+    /// How the quine was verified
+    pub method: VerifyMethod,
+    /// Whether the program prints exactly its own source
+    pub reproduces: bool,
+    /// Size of the source in bytes
+    pub source_bytes: usize,
+    /// Size of the printed output in bytes
+    pub output_bytes: usize,
+    /// Byte offset of the first difference between source and output
+    pub first_mismatch: Option<usize>,
+}
+
+impl VerifyReport {
+    fn compare(method: VerifyMethod, expected: &[u8], actual: &[u8]) -> Self {
+        let first_mismatch = if expected == actual {
+            None
+        } else {
+            Some(
+                expected
+                    .iter()
+                    .zip(actual)
+                    .position(|(a, b)| a != b)
+                    .unwrap_or_else(|| expected.len().min(actual.len())),
+            )
+        };
+        Self {
+            method,
+            reproduces: first_mismatch.is_none(),
+            source_bytes: expected.len(),
+            output_bytes: actual.len(),
+            first_mismatch,
+        }
+    }
+}
+
+/// Structure of a recognized quine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuineAnalysis {
+    /// Language of the quine
+    pub language: Language,
+    /// Text of the header comment, if any
+    pub header: Option<String>,
+    /// Number of lines in the program
+    pub line_count: usize,
+    /// Variable holding the program source without its data literal
+    pub data_identifier: String,
+    /// UTF-16 offset at which the data literal is re-inserted
+    pub literal_offset: usize,
+    /// How the program rebuilds the data literal at runtime
+    pub literal_rebuild: String,
+    /// Variable holding the payload
+    pub payload_identifier: Option<String>,
+    /// The embedded payload
+    pub payload: Option<String>,
+    /// Escape strategy the payload literal appears to use
+    pub escape_strategy: Option<EscapeStrategy>,
+}
+
+impl QuineAnalysis {
+    /// Human-readable explanation of how the quine works
+    pub fn explain(&self) -> String {
+        let mut lines = vec![
+            format!("Language:         {}", self.language.name()),
+            format!(
+                "Layout:           {} ({} line{})",
+                if self.line_count > 1 {
+                    "multi-line"
+                } else {
+                    "one-line"
+                },
+                self.line_count,
+                if self.line_count == 1 { "" } else { "s" }
+            ),
+        ];
+        if let Some(header) = &self.header {
+            lines.push(format!(
+                "Header:           {} line(s), reproduced as part of the source",
+                header.lines().count()
+            ));
+        }
+        lines.push(format!(
+            "Data variable:    {} (the program source with its own literal removed)",
+            self.data_identifier
+        ));
+        lines.push(format!(
+            "Literal offset:   {} (UTF-16 units)",
+            self.literal_offset
+        ));
+        lines.push(format!("Literal rebuild:  {}", self.literal_rebuild));
+        match (&self.payload_identifier, &self.payload) {
+            (Some(ident), Some(payload)) => lines.push(format!(
+                "Payload:          {} = {} bytes ({} escaping)",
+                ident,
+                payload.len(),
+                self.escape_strategy.map(|s| s.name()).unwrap_or("unknown")
+            )),
+            _ => lines.push("Payload:          none found".to_string()),
+        }
+        lines.push(String::new());
+        lines.push(format!(
+            "At runtime the program prints the first {n} units of `{d}`, then `{d}` \
+             re-encoded as a string literal via {rebuild}, then the rest of `{d}`. \
+             That is exactly its own source, so it is a quine.",
+            n = self.literal_offset,
+            d = self.data_identifier,
+            rebuild = self.literal_rebuild,
+        ));
+        lines.join("\n")
+    }
+}
+
+/// The data literal of a quine and what is needed to rebuild it
+struct Located {
+    code: String,
+    data: String,
+    offset: usize,
+    data_identifier: String,
+    codec: DataCodec,
+}
+
+/// Strip the single trailing newline that terminates a quine file
+fn program_code(source: &str) -> &str {
+    source
+        .strip_suffix("\r\n")
+        .or_else(|| source.strip_suffix('\n'))
+        .unwrap_or(source)
+}
+
+/// Find the data literal via the offset in the print statement
+fn locate(source: &str) -> Result<Located, QuineError> {
+    let code = program_code(source);
+    let unrecognized = |reason: &str| QuineError::UnrecognizedQuine(reason.to_string());
+
+    let (call_start, call_len) = [".slice(0,", ".substring(0,"]
+        .iter()
+        .filter_map(|pattern| code.rfind(pattern).map(|pos| (pos, pattern.len())))
+        .max()
+        .ok_or_else(|| unrecognized("no slice of the data variable found"))?;
+
+    let digits: String = code[call_start + call_len..]
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    let offset: usize = digits
+        .parse()
+        .map_err(|_| unrecognized("slice offset is not a number"))?;
+
+    let data_identifier: String = code[..call_start]
+        .chars()
+        .rev()
+        .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '$')
+        .collect::<Vec<char>>()
+        .into_iter()
+        .rev()
+        .collect();
+    if data_identifier.is_empty() {
+        return Err(unrecognized("data variable not found"));
+    }
+
+    let start = utf16_to_byte_offset(code, offset)
+        .ok_or_else(|| unrecognized("slice offset is outside the source"))?;
+    let mut stream = serde_json::Deserializer::from_str(&code[start..]).into_iter::<String>();
+    let data = match stream.next() {
+        Some(Ok(data)) => data,
+        _ => return Err(unrecognized("no data literal at the slice offset")),
+    };
+
+    let codec = if code.contains("JSON.stringify(") {
+        DataCodec::Json
+    } else {
+        DataCodec::SplitJoin(QuoteSource::FromCharCode)
+    };
+
+    Ok(Located {
+        code: code.to_string(),
+        data,
+        offset,
+        data_identifier,
+        codec,
+    })
+}
+
+/// Analyze the structure of a quine
+pub fn analyze(source: &str) -> Result<QuineAnalysis, QuineError> {
+    let located = locate(source)?;
+    let tokens: Vec<(TokenKind, &str)> = tokenize(&located.data)
+        .into_iter()
+        .filter(|(kind, _)| *kind != TokenKind::Whitespace)
+        .collect();
+
+    // Leading comments form the header
+    let header_lines: Vec<String> = tokens
+        .iter()
+        .take_while(|(kind, _)| *kind == TokenKind::Comment)
+        .flat_map(|(_, text)| comment_lines(text))
+        .collect();
+
+    // Identifiers the print statement uses; the payload variable is the
+    // string declaration that it does not use
+    let print_start = tokens
+        .iter()
+        .position(|(_, text)| *text == "console")
+        .unwrap_or(tokens.len());
+    let used: Vec<&str> = tokens[print_start..]
+        .iter()
+        .filter(|(kind, _)| *kind == TokenKind::Identifier)
+        .map(|(_, text)| *text)
+        .collect();
+
+    let payload_declaration = tokens[..print_start].windows(3).find_map(|window| {
+        match window {
+            [(TokenKind::Identifier, ident), (TokenKind::Punctuation, "="), (TokenKind::String, literal)]
+                if !used.contains(ident) =>
+            {
+                Some((ident.to_string(), *literal))
+            }
+            _ => None,
+        }
+    });
+
+    let (payload_identifier, payload, escape_strategy) = match payload_declaration {
+        Some((ident, literal)) => {
+            let body = string_body(literal).map(|(_, body)| body);
+            let payload = body.and_then(unescape_js);
+            (Some(ident), payload, body.and_then(guess_escape_strategy))
+        }
+        None => (None, None, None),
+    };
+
+    let literal_rebuild = match located.codec {
+        DataCodec::Json => "JSON.stringify",
+        DataCodec::SplitJoin(_) => "split/join escaping with a quote variable",
+    };
+
+    Ok(QuineAnalysis {
+        language: Language::JavaScript,
+        header: (!header_lines.is_empty()).then(|| header_lines.join("\n")),
+        line_count: located.code.lines().count(),
+        data_identifier: located.data_identifier,
+        literal_offset: located.offset,
+        literal_rebuild: literal_rebuild.to_string(),
+        payload_identifier,
+        payload,
+        escape_strategy,
+    })
+}
+
+/// Recover the payload embedded in a quine
+pub fn extract(source: &str) -> Result<String, QuineError> {
+    analyze(source)?
+        .payload
+        .ok_or_else(|| QuineError::UnrecognizedQuine("no payload literal found".to_string()))
+}
+
+/// Verify a quine statically by rebuilding its output from the data literal
+pub fn verify(source: &str) -> Result<VerifyReport, QuineError> {
+    let located = locate(source)?;
+    let split = utf16_to_byte_offset(&located.data, located.offset).ok_or_else(|| {
+        QuineError::UnrecognizedQuine("slice offset is outside the data".to_string())
+    })?;
+    let variant = TemplateVariant {
+        codec: located.codec,
+        ..TemplateVariant::default()
+    };
+    let printed = format!(
+        "{}{}{}",
+        &located.data[..split],
+        variant.encode(&located.data),
+        &located.data[split..]
+    );
+    Ok(VerifyReport::compare(
+        VerifyMethod::Static,
+        located.code.as_bytes(),
+        printed.as_bytes(),
+    ))
+}
+
+/// How long `verify_with_interpreter` lets a program run
+pub const INTERPRETER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often a running interpreter is checked on
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Verify a quine by running it with the language's interpreter
+///
+/// A program still running after `INTERPRETER_TIMEOUT` is killed and
+/// reported as `QuineError::Timeout`.
+pub fn verify_with_interpreter(
+    source: &str,
+    language: Language,
+) -> Result<VerifyReport, QuineError> {
+    verify_with_interpreter_timeout(source, language, INTERPRETER_TIMEOUT)
+}
+
+/// `verify_with_interpreter`, killing the program after `timeout`
+pub fn verify_with_interpreter_timeout(
+    source: &str,
+    language: Language,
+    timeout: Duration,
+) -> Result<VerifyReport, QuineError> {
+    let command = language.interpreter();
+    let mut child = Command::new(command[0])
+        .args(&command[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| QuineError::InterpreterError(format!("{}: {}", command[0], e)))?;
+
+    // Feed the program from another thread so a chatty child can't deadlock us
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let program = source.to_string();
+    let writer = thread::spawn(move || stdin.write_all(program.as_bytes()));
+    let stdout = read_in_background(child.stdout.take().expect("stdout is piped"));
+    let stderr = read_in_background(child.stderr.take().expect("stderr is piped"));
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child
+            .try_wait()
+            .map_err(|e| QuineError::InterpreterError(e.to_string()))?
+        {
+            break status;
+        }
+        if started.elapsed() >= timeout {
+            // Killing closes the pipes, which ends the threads
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(QuineError::Timeout(timeout));
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+    let stdout = stdout
+        .join()
+        .expect("stdout reader panicked")
+        .map_err(|e| QuineError::InterpreterError(e.to_string()))?;
+    let stderr = stderr
+        .join()
+        .expect("stderr reader panicked")
+        .map_err(|e| QuineError::InterpreterError(e.to_string()))?;
+    writer
+        .join()
+        .expect("stdin writer panicked")
+        .map_err(|e| QuineError::InterpreterError(e.to_string()))?;
+
+    if !status.success() {
+        return Err(QuineError::InterpreterError(format!(
+            "{} exited with {}: {}",
+            command[0],
+            status,
+            String::from_utf8_lossy(&stderr).trim()
+        )));
+    }
+
+    // The print statement terminates the output with a newline
+    let expected = format!("{}\n", program_code(source));
+    Ok(VerifyReport::compare(
+        VerifyMethod::Runtime,
+        expected.as_bytes(),
+        &stdout,
+    ))
+}
+
+/// Read all of `pipe` on another thread
+fn read_in_background(
+    mut pipe: impl Read + Send + 'static,
+) -> thread::JoinHandle<std::io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        pipe.read_to_end(&mut bytes).map(|_| bytes)
+    })
+}
+
+/// Text lines of a comment token, without comment markers
+fn comment_lines(comment: &str) -> Vec<String> {
+    let inner = comment
+        .strip_prefix("/*")
+        .and_then(|c| c.strip_suffix("*/"))
+        .or_else(|| comment.strip_prefix("//"))
+        .unwrap_or(comment);
+    inner
+        .lines()
+        .map(|line| {
+            let line = line.trim();
+            line.strip_prefix('*').unwrap_or(line).trim().to_string()
+        })
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Infer the escape strategy from the escapes a literal body uses
+fn guess_escape_strategy(body: &str) -> Option<EscapeStrategy> {
+    if body.contains("\\u{") {
+        Some(EscapeStrategy::Unicode)
+    } else if ["\\n", "\\r", "\\t", "\\'", "\\0"]
+        .iter()
+        .any(|escape| body.contains(escape))
+        || !body.is_ascii()
+    {
+        Some(EscapeStrategy::Standard)
+    } else if body.contains("\\x") {
+        Some(EscapeStrategy::Hexadecimal)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formatter::{FormatOptions, OutputFormat};
+    use crate::generator::QuineGenerator;
+    use crate::header::HeaderOptions;
+    use crate::style::StyleProfile;
+
+    #[test]
+    fn test_verify_generated_quines() {
+        for seed in 0..16 {
+            let options = FormatOptions::default()
+                .with_style(StyleProfile::standard())
+                .with_variant_seed(seed);
+            let output = QuineGenerator::with_options(options)
+                .generate("payload")
+                .unwrap();
+            assert!(
+                verify(&output.one_line).unwrap().reproduces,
+                "seed {}",
+                seed
+            );
+            assert!(
+                verify(&output.multi_line).unwrap().reproduces,
+                "seed {}",
+                seed
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_detects_tampering() {
+        let output = QuineGenerator::new().generate("payload").unwrap();
+        let tampered = format!("{};", output.one_line.trim_end());
+        let report = verify(&tampered).unwrap();
+        assert!(!report.reproduces);
+        assert!(report.first_mismatch.is_some());
+    }
+
+    #[test]
+    fn test_extract_payload() {
+        let payload = "multi\nline \"payload\" with 'quotes' and 🦀";
+        for strategy in EscapeStrategy::ALL {
+            for seed in 0..8 {
+                let options = FormatOptions::default()
+                    .with_escape_strategy(*strategy)
+                    .with_variant_seed(seed);
+                let output = QuineGenerator::with_options(options)
+                    .generate(payload)
+                    .unwrap();
+                assert_eq!(extract(&output.one_line).unwrap(), payload);
+                assert_eq!(extract(&output.multi_line).unwrap(), payload);
+            }
+        }
+    }
+
+    #[test]
+    fn test_analyze_reports_structure() {
+        let header = HeaderOptions::new().with_license("SPDX-License-Identifier: MIT");
+        let options = FormatOptions::default()
+            .with_escape_strategy(EscapeStrategy::Unicode)
+            .with_header(header);
+        let output = QuineGenerator::with_options(options).generate("é").unwrap();
+        let analysis = analyze(&output.multi_line).unwrap();
+
+        assert_eq!(
+            analysis.header.as_deref(),
+            Some("SPDX-License-Identifier: MIT")
+        );
+        assert_eq!(analysis.data_identifier, "d");
+        assert_eq!(analysis.payload_identifier.as_deref(), Some("p"));
+        assert_eq!(analysis.escape_strategy, Some(EscapeStrategy::Unicode));
+        assert!(analysis.line_count > 1);
+        assert!(analysis
+            .explain()
+            .contains("Literal rebuild:  JSON.stringify"));
+    }
+
+    #[test]
+    fn test_unrecognized_source() {
+        assert!(matches!(
+            analyze("console.log('hello')"),
+            Err(QuineError::UnrecognizedQuine(_))
+        ));
+    }
+
+    #[test]
+    fn test_interpreter_timeout() {
+        let language = Language::JavaScript;
+        if Command::new(language.interpreter()[0])
+            .arg("--version")
+            .output()
+            .is_err()
+        {
+            return; // no interpreter to run
+        }
+        let timeout = Duration::from_millis(500);
+        let started = Instant::now();
+        let result = verify_with_interpreter_timeout("for (;;) {}\n", language, timeout);
+        assert!(matches!(result, Err(QuineError::Timeout(t)) if t == timeout));
+        assert!(started.elapsed() < INTERPRETER_TIMEOUT);
+
+        let output = QuineGenerator::new().generate("hi").unwrap();
+        let report =
+            verify_with_interpreter(&output.source(OutputFormat::OneLine), language).unwrap();
+        assert!(report.reproduces);
+    }
+}
@@ -0,0 +1,111 @@
+//! JavaScript bindings for the WebAssembly build (`wasm` feature)
+//!
+//! Built with `wasm-pack build core --target web -- --no-default-features
+//! --features wasm`. Values cross the boundary as plain JavaScript objects
+//! with the same shape as the JSON the other front ends use: `generate`
+//! returns a `QuineOutput`, `generateWithOptions` takes a `GenerateRequest`.
+//! Failures are thrown as `{ kind, message }` objects.
+//!
+//! Only helpers that don't run an interpreter are exposed; `verify` checks
+//! a quine statically.
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::generator::QuineError;
+use crate::request::{GenerateRequest, ParseOptionError};
+use crate::templates::Templates;
+
+/// A failure as thrown to JavaScript
+#[derive(Serialize)]
+struct JsFailure {
+    kind: &'static str,
+    message: String,
+}
+
+impl JsFailure {
+    fn throw(self) -> JsValue {
+        to_js(&self)
+    }
+}
+
+impl From<QuineError> for JsFailure {
+    fn from(error: QuineError) -> Self {
+        Self {
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<ParseOptionError> for JsFailure {
+    fn from(error: ParseOptionError) -> Self {
+        Self {
+            kind: "invalid_option",
+            message: error.to_string(),
+        }
+    }
+}
+
+fn to_js<T: Serialize>(value: &T) -> JsValue {
+    value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .expect("values serialize")
+}
+
+fn result<T: Serialize>(result: Result<T, impl Into<JsFailure>>) -> Result<JsValue, JsValue> {
+    result
+        .map(|value| to_js(&value))
+        .map_err(|e| e.into().throw())
+}
+
+/// Generate a quine with the default options
+#[wasm_bindgen]
+pub fn generate(input: &str) -> Result<JsValue, JsValue> {
+    result(crate::generate(input))
+}
+
+/// Generate a quine with options such as
+/// `{ escape_strategy: "unicode", style: "allman" }`
+#[wasm_bindgen(js_name = generateWithOptions)]
+pub fn generate_with_options(input: &str, options: JsValue) -> Result<JsValue, JsValue> {
+    let request: GenerateRequest = if options.is_undefined() || options.is_null() {
+        GenerateRequest::default()
+    } else {
+        serde_wasm_bindgen::from_value(options).map_err(|e| {
+            JsFailure {
+                kind: "invalid_option",
+                message: e.to_string(),
+            }
+            .throw()
+        })?
+    };
+    let generator = request
+        .generator()
+        .map_err(|e| JsFailure::from(e).throw())?;
+    result(generator.generate(input))
+}
+
+/// Check statically that `source` prints itself, returning a `VerifyReport`
+#[wasm_bindgen]
+pub fn verify(source: &str) -> Result<JsValue, JsValue> {
+    result(crate::verify(source))
+}
+
+/// Recover the payload embedded in a quine
+#[wasm_bindgen]
+pub fn extract(source: &str) -> Result<String, JsValue> {
+    crate::extract(source).map_err(|e| JsFailure::from(e).throw())
+}
+
+/// Describe how a quine reproduces itself, as a `QuineAnalysis`
+#[wasm_bindgen]
+pub fn analyze(source: &str) -> Result<JsValue, JsValue> {
+    result(crate::analyze(source))
+}
+
+/// Languages, escape strategies and style presets
+#[wasm_bindgen]
+pub fn templates() -> JsValue {
+    to_js(&Templates::all())
+}
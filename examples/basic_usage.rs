@@ -65,9 +65,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("Example 3: Custom formatting\n");
 
-    let custom_options = FormatOptions::default()
-        .with_indent("  ")
-        .with_max_line_length(120);
+    let custom_options = FormatOptions::default().with_indent("  ");
 
     let custom_gen = QuineGenerator::with_options(custom_options);
     let custom_output = custom_gen.generate("function test() { return 42; }")?;
@@ -88,7 +86,6 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let generator = QuineGeneratorBuilder::new()
         .escape_strategy(EscapeStrategy::Hexadecimal)
         .indent("    ")
-        .max_line_length(100)
         .max_input_size(1024 * 1024)
         .build();
 
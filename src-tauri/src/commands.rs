@@ -2,73 +2,367 @@
 //!
 //! Exposes the core quine generation functionality to the frontend.
 
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
 use psychoquine_core::{
-    EscapeStrategy, FormatOptions, QuineError, QuineGenerator, QuineOutput,
+    verify, verify_with_interpreter, CancellationToken, EscapeStrategyInfo, GenerateRequest,
+    Language, OutputFormat, Progress, QuineOutput, Templates, VerifyMethod, VerifyReport,
 };
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
+use tauri::{State, Window};
 
-/// Options passed from the frontend
-#[derive(Debug, Deserialize)]
-pub struct GenerateOptions {
-    pub escape_strategy: Option<String>,
-    pub indent: Option<String>,
-    pub max_line_length: Option<usize>,
-}
+use crate::error::{CommandError, INPUT_NOT_KEPT, NOT_FOUND, TASK_FAILED};
+use crate::history::{History, HistoryEntry, HistorySummary, MAX_STORED_INPUT};
+use crate::input::{read_input_file, FileInfo};
+
+/// Event emitted while `generate_quine_async` or
+/// `generate_quine_from_file` runs
+pub const PROGRESS_EVENT: &str = "generation-progress";
 
 /// Response structure for the frontend
 #[derive(Debug, Serialize)]
-pub struct GenerateResponse {
+pub struct Response<T> {
     pub success: bool,
-    pub data: Option<QuineOutput>,
-    pub error: Option<String>,
+    pub data: Option<T>,
+    pub error: Option<CommandError>,
 }
 
-impl From<Result<QuineOutput, QuineError>> for GenerateResponse {
-    fn from(result: Result<QuineOutput, QuineError>) -> Self {
+impl<T, E: Into<CommandError>> From<Result<T, E>> for Response<T> {
+    fn from(result: Result<T, E>) -> Self {
         match result {
-            Ok(output) => GenerateResponse {
+            Ok(data) => Response {
                 success: true,
-                data: Some(output),
+                data: Some(data),
                 error: None,
             },
-            Err(e) => GenerateResponse {
+            Err(e) => Response {
                 success: false,
                 data: None,
-                error: Some(e.to_string()),
+                error: Some(e.into()),
             },
         }
     }
 }
 
-/// Parse escape strategy from string
-fn parse_escape_strategy(s: &str) -> EscapeStrategy {
-    match s.to_lowercase().as_str() {
-        "unicode" => EscapeStrategy::Unicode,
-        "hex" | "hexadecimal" => EscapeStrategy::Hexadecimal,
-        "raw" => EscapeStrategy::Raw,
-        _ => EscapeStrategy::Standard,
+/// Response to `generate_quine`
+pub type GenerateResponse = Response<QuineOutput>;
+
+/// Payload of `PROGRESS_EVENT`
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressEvent {
+    pub job_id: u64,
+    #[serde(flatten)]
+    pub progress: Progress,
+}
+
+/// Cancellation tokens of running generations, by job id
+///
+/// Job ids come from the frontend and may be reused, so each start also
+/// gets a serial number and only the job holding it can remove its entry.
+#[derive(Debug, Default)]
+pub struct Jobs(Mutex<JobTable>);
+
+#[derive(Debug, Default)]
+struct JobTable {
+    next_serial: u64,
+    running: HashMap<u64, (u64, CancellationToken)>,
+}
+
+impl Jobs {
+    /// Register a generation and return its serial number and token
+    fn start(&self, job_id: u64) -> (u64, CancellationToken) {
+        let cancel = CancellationToken::new();
+        let mut table = self.0.lock().unwrap();
+        table.next_serial += 1;
+        let serial = table.next_serial;
+        table.running.insert(job_id, (serial, cancel.clone()));
+        (serial, cancel)
+    }
+
+    /// Cancel a generation, returning whether it was running
+    fn cancel(&self, job_id: u64) -> bool {
+        match self.0.lock().unwrap().running.get(&job_id) {
+            Some((_, cancel)) => {
+                cancel.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forget the generation started as `serial`, unless a newer one has
+    /// taken over its id
+    fn finish(&self, job_id: u64, serial: u64) {
+        let mut table = self.0.lock().unwrap();
+        if table
+            .running
+            .get(&job_id)
+            .is_some_and(|(current, _)| *current == serial)
+        {
+            table.running.remove(&job_id);
+        }
     }
 }
 
-/// Generate a quine from the input
+/// A quine written to disk
+#[derive(Debug, Serialize)]
+pub struct SavedQuine {
+    pub path: String,
+    pub bytes: usize,
+}
+
+/// A history entry with its quines
+#[derive(Debug, Serialize)]
+pub struct ReopenedEntry {
+    #[serde(flatten)]
+    pub entry: HistoryEntry,
+    pub output: QuineOutput,
+}
+
+/// A quine generated from a file, with what was read
+#[derive(Debug, Serialize)]
+pub struct FileQuine {
+    pub file: FileInfo,
+    pub output: QuineOutput,
+}
+
+/// Generate a quine from the input and record it in the history
 #[tauri::command]
-pub fn generate_quine(input: String, options: Option<GenerateOptions>) -> GenerateResponse {
-    let mut format_options = FormatOptions::default();
+pub fn generate_quine(
+    history: State<'_, History>,
+    input: String,
+    options: Option<GenerateRequest>,
+) -> GenerateResponse {
+    let result = generate_output(&input, options.clone());
+    if let Ok(output) = &result {
+        record(&history, options, output);
+    }
+    result.into()
+}
 
-    if let Some(opts) = options {
-        if let Some(strategy) = opts.escape_strategy {
-            format_options.escape_strategy = parse_escape_strategy(&strategy);
-        }
-        if let Some(indent) = opts.indent {
-            format_options.indent = indent;
+fn generate_output(
+    input: &str,
+    options: Option<GenerateRequest>,
+) -> Result<QuineOutput, CommandError> {
+    let generator = options.unwrap_or_default().generator()?;
+    Ok(generator.generate(input)?)
+}
+
+/// Generate a quine off the main thread, emitting `PROGRESS_EVENT` for
+/// `job_id` until it finishes or `cancel_generation` stops it, and record
+/// it in the history
+#[tauri::command]
+pub async fn generate_quine_async(
+    window: Window,
+    jobs: State<'_, Jobs>,
+    history: State<'_, History>,
+    job_id: u64,
+    input: String,
+    options: Option<GenerateRequest>,
+) -> Result<GenerateResponse, ()> {
+    let generator = match options.clone().unwrap_or_default().generator() {
+        Ok(generator) => generator,
+        Err(e) => return Ok(Err(e).into()),
+    };
+    let result = run_job(window, &jobs, job_id, move |cancel, on_progress| {
+        Ok(generator.generate_with_progress(&input, cancel, on_progress)?)
+    })
+    .await;
+    if let Ok(output) = &result {
+        record(&history, options, output);
+    }
+    Ok(result.into())
+}
+
+/// Read the file at `path`, dropped on the window or picked in a file
+/// dialog, and generate a quine from it like `generate_quine_async`
+///
+/// The file is refused before it is read when it is larger than the
+/// generator's maximum input size.
+#[tauri::command]
+pub async fn generate_quine_from_file(
+    window: Window,
+    jobs: State<'_, Jobs>,
+    history: State<'_, History>,
+    job_id: u64,
+    path: String,
+    options: Option<GenerateRequest>,
+) -> Result<Response<FileQuine>, ()> {
+    let generator = match options.clone().unwrap_or_default().generator() {
+        Ok(generator) => generator,
+        Err(e) => return Ok(Err(e).into()),
+    };
+    let result = run_job(window, &jobs, job_id, move |cancel, on_progress| {
+        let (input, file) = read_input_file(Path::new(&path), generator.max_input_size())?;
+        let output = generator.generate_with_progress(&input, cancel, on_progress)?;
+        Ok(FileQuine { file, output })
+    })
+    .await;
+    if let Ok(quine) = &result {
+        record(&history, options, &quine.output);
+    }
+    Ok(result.into())
+}
+
+/// Run `job` off the main thread as `job_id`, emitting `PROGRESS_EVENT`
+/// for each report it makes
+async fn run_job<T: Send + 'static>(
+    window: Window,
+    jobs: &Jobs,
+    job_id: u64,
+    job: impl FnOnce(&CancellationToken, &mut dyn FnMut(Progress)) -> Result<T, CommandError>
+        + Send
+        + 'static,
+) -> Result<T, CommandError> {
+    let (serial, cancel) = jobs.start(job_id);
+    let task = tauri::async_runtime::spawn_blocking(move || {
+        job(&cancel, &mut |progress| {
+            // The result is returned either way; a lost event only skips an update
+            let _ = window.emit(PROGRESS_EVENT, ProgressEvent { job_id, progress });
+        })
+    });
+    let result = task.await;
+    jobs.finish(job_id, serial);
+
+    result.unwrap_or_else(|e| {
+        Err(CommandError::new(
+            TASK_FAILED,
+            format!("generation task failed: {}", e),
+        ))
+    })
+}
+
+fn record(history: &History, options: Option<GenerateRequest>, output: &QuineOutput) {
+    // Losing a history entry must not lose the quine the user just made
+    if let Err(e) = history.record(options, output) {
+        eprintln!("cannot record history: {}", e);
+    }
+}
+
+/// Stop a generation started with `generate_quine_async`, returning whether
+/// it was still running
+#[tauri::command]
+pub fn cancel_generation(jobs: State<'_, Jobs>, job_id: u64) -> bool {
+    jobs.cancel(job_id)
+}
+
+/// Check that a quine reproduces itself, statically from its data literal
+/// (the default) or by running it
+///
+/// Runs off the main thread, since running the quine can take as long as
+/// the interpreter's timeout.
+#[tauri::command]
+pub async fn verify_quine(
+    source: String,
+    language: Option<String>,
+    method: Option<VerifyMethod>,
+) -> Response<VerifyReport> {
+    let language = match language.as_deref().map(str::parse::<Language>).transpose() {
+        Ok(language) => language.unwrap_or_default(),
+        Err(e) => return Err(e).into(),
+    };
+    let task = tauri::async_runtime::spawn_blocking(move || {
+        match method.unwrap_or(VerifyMethod::Static) {
+            VerifyMethod::Static => verify(&source),
+            VerifyMethod::Runtime => verify_with_interpreter(&source, language),
         }
-        if let Some(max_len) = opts.max_line_length {
-            format_options.max_line_length = max_len;
+    });
+    match task.await {
+        Ok(result) => result.into(),
+        Err(e) => Err(CommandError::new(
+            TASK_FAILED,
+            format!("verification task failed: {}", e),
+        ))
+        .into(),
+    }
+}
+
+/// List languages, escape strategies and style presets
+#[tauri::command]
+pub fn list_templates() -> Templates {
+    Templates::all()
+}
+
+/// List escape strategies with their descriptions
+#[tauri::command]
+pub fn list_escape_strategies() -> Vec<EscapeStrategyInfo> {
+    Templates::all().escape_strategies
+}
+
+/// Write the `one-line` or `multi-line` quine of `output` to `path`
+#[tauri::command]
+pub fn save_quine(path: String, variant: String, output: QuineOutput) -> Response<SavedQuine> {
+    let format = match variant.parse() {
+        Ok(format @ (OutputFormat::OneLine | OutputFormat::MultiLine)) => format,
+        _ => {
+            let expected = [OutputFormat::OneLine.name(), OutputFormat::MultiLine.name()];
+            return Err(CommandError::invalid_option("variant", &variant, &expected)).into();
         }
+    };
+    let source = output.source(format);
+    fs::write(&path, &source)
+        .map(|()| SavedQuine {
+            path: path.clone(),
+            bytes: source.len(),
+        })
+        .map_err(|e| CommandError::io(format!("cannot write {}", path), e))
+        .into()
+}
+
+/// List recorded generations, newest first
+#[tauri::command]
+pub fn list_history(history: State<'_, History>) -> Vec<HistorySummary> {
+    history.list()
+}
+
+/// A recorded generation, with its quines generated again from the kept
+/// input and options
+#[tauri::command]
+pub fn reopen_history(history: State<'_, History>, id: u64) -> Response<ReopenedEntry> {
+    reopen(&history, id).into()
+}
+
+fn reopen(history: &History, id: u64) -> Result<ReopenedEntry, CommandError> {
+    let entry = history.get(id).ok_or_else(|| not_found(id))?;
+    let Some(input) = &entry.input else {
+        return Err(CommandError::new(
+            INPUT_NOT_KEPT,
+            format!(
+                "history entry {} was over {} bytes, so its input was not kept",
+                id, MAX_STORED_INPUT
+            ),
+        ));
+    };
+    let output = generate_output(input, entry.options.clone())?;
+    Ok(ReopenedEntry { entry, output })
+}
+
+/// Pin an entry so it is never dropped, or unpin it
+#[tauri::command]
+pub fn pin_history(history: State<'_, History>, id: u64, pinned: bool) -> Response<()> {
+    history_update(id, history.set_pinned(id, pinned))
+}
+
+/// Delete a recorded generation
+#[tauri::command]
+pub fn delete_history(history: State<'_, History>, id: u64) -> Response<()> {
+    history_update(id, history.delete(id))
+}
+
+fn history_update(id: u64, result: std::io::Result<bool>) -> Response<()> {
+    match result {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(not_found(id)),
+        Err(e) => Err(CommandError::io("cannot write history", e)),
     }
+    .into()
+}
 
-    let generator = QuineGenerator::with_options(format_options);
-    generator.generate(&input).into()
+fn not_found(id: u64) -> CommandError {
+    CommandError::new(NOT_FOUND, format!("no history entry {}", id))
 }
 
 /// Get application version
@@ -81,28 +375,182 @@ pub fn get_version() -> String {
 mod tests {
     use super::*;
 
+    use psychoquine_core::{EscapeStrategy, Indent, QuineError};
+    use tauri::async_runtime::block_on;
+
     #[test]
     fn test_generate_quine_command() {
-        let response = generate_quine("test".to_string(), None);
+        let response = GenerateResponse::from(generate_output("test", None));
         assert!(response.success);
         assert!(response.data.is_some());
     }
 
     #[test]
     fn test_generate_with_options() {
-        let options = GenerateOptions {
+        let options = GenerateRequest {
             escape_strategy: Some("unicode".to_string()),
-            indent: Some("  ".to_string()),
-            max_line_length: Some(120),
+            indent: Some(Indent::Text("  ".to_string())),
+            language: Some("javascript".to_string()),
+            ..GenerateRequest::default()
         };
-        let response = generate_quine("test".to_string(), Some(options));
+        let response = GenerateResponse::from(generate_output("test", Some(options)));
         assert!(response.success);
     }
 
     #[test]
     fn test_empty_input_error() {
-        let response = generate_quine("".to_string(), None);
+        let response = GenerateResponse::from(generate_output("", None));
         assert!(!response.success);
         assert!(response.error.is_some());
     }
+
+    #[test]
+    fn test_unknown_option_values_are_rejected() {
+        let options = GenerateRequest {
+            escape_strategy: Some("rot13".to_string()),
+            ..GenerateRequest::default()
+        };
+        let error = generate_output("test", Some(options)).unwrap_err();
+        assert_eq!(error.kind, "invalid_option");
+        assert_eq!(error.details.unwrap()["value"], "rot13");
+
+        let error = block_on(verify_quine(String::new(), Some("cobol".to_string()), None))
+            .error
+            .unwrap();
+        assert_eq!(error.kind, "invalid_option");
+    }
+
+    #[test]
+    fn test_frontend_payload() {
+        // The names the frontend sends, in any case, and its string indent
+        let options: GenerateRequest = serde_json::from_value(serde_json::json!({
+            "escape_strategy": "Hexadecimal",
+            "indent": "\t",
+        }))
+        .unwrap();
+        let output = generate_output("test", Some(options)).unwrap();
+        assert_eq!(output.escape_strategy, EscapeStrategy::Hexadecimal);
+        assert_eq!(
+            serde_json::to_value(&output).unwrap()["escape_strategy"],
+            "hex"
+        );
+        assert!(output.multi_line.contains("\n\t"));
+    }
+
+    #[test]
+    fn test_reopen_regenerates_output() {
+        let dir = std::env::temp_dir().join(format!("psychoquine-reopen-{}", std::process::id()));
+        let history = History::open(dir.join("history.jsonl")).unwrap();
+        let options = GenerateRequest {
+            escape_strategy: Some("unicode".to_string()),
+            ..GenerateRequest::default()
+        };
+        let output = generate_output("test", Some(options.clone())).unwrap();
+        let entry = history.record(Some(options), &output).unwrap();
+
+        let reopened = reopen(&history, entry.id).unwrap();
+        assert_eq!(reopened.output.one_line, output.one_line);
+        let payload = serde_json::to_value(&reopened).unwrap();
+        assert_eq!(payload["id"], entry.id);
+        assert_eq!(payload["output"]["one_line"], output.one_line);
+        assert_eq!(reopen(&history, entry.id + 1).unwrap_err().kind, NOT_FOUND);
+
+        let large = generate_output(&"x".repeat(MAX_STORED_INPUT + 1), None).unwrap();
+        let entry = history.record(None, &large).unwrap();
+        assert_eq!(reopen(&history, entry.id).unwrap_err().kind, INPUT_NOT_KEPT);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cancel_running_job() {
+        let jobs = Jobs::default();
+        let (serial, cancel) = jobs.start(7);
+        assert!(!jobs.cancel(8));
+        assert!(jobs.cancel(7));
+
+        let result = GenerateRequest::default()
+            .generator()
+            .unwrap()
+            .generate_with_progress("test", &cancel, |_| {});
+        assert!(matches!(result, Err(QuineError::Cancelled)));
+        jobs.finish(7, serial);
+        assert!(!jobs.cancel(7));
+    }
+
+    #[test]
+    fn test_reused_job_id_keeps_newer_job() {
+        let jobs = Jobs::default();
+        let (old_serial, old_cancel) = jobs.start(1);
+        let (new_serial, new_cancel) = jobs.start(1);
+
+        // The old job finishing must not unregister the new one
+        jobs.finish(1, old_serial);
+        assert!(jobs.cancel(1));
+        assert!(new_cancel.is_cancelled());
+        assert!(!old_cancel.is_cancelled());
+
+        jobs.finish(1, new_serial);
+        assert!(!jobs.cancel(1));
+    }
+
+    #[test]
+    fn test_progress_event_payload() {
+        let mut events = Vec::new();
+        GenerateRequest::default()
+            .generator()
+            .unwrap()
+            .generate_with_progress("test", &CancellationToken::new(), |progress| {
+                events.push(ProgressEvent {
+                    job_id: 1,
+                    progress,
+                })
+            })
+            .unwrap();
+        let last = serde_json::to_value(events.last().unwrap()).unwrap();
+        assert_eq!(
+            last,
+            serde_json::json!({"job_id": 1, "phase": "Done", "bytes_escaped": 4, "total_bytes": 4})
+        );
+    }
+
+    #[test]
+    fn test_verify_generated_quine() {
+        let output = generate_output("test", None).unwrap();
+        let response = block_on(verify_quine(
+            output.source(OutputFormat::OneLine),
+            None,
+            None,
+        ));
+        assert!(response.data.unwrap().reproduces);
+
+        // Static verification is the default, so no interpreter is needed
+        let response = block_on(verify_quine("console.log(1)".to_string(), None, None));
+        assert_eq!(response.error.unwrap().kind, "unrecognized_quine");
+    }
+
+    #[test]
+    fn test_list_templates() {
+        let templates = list_templates();
+        assert!(templates.languages.iter().any(|l| l.name == "javascript"));
+        assert_eq!(templates.escape_strategies.len(), EscapeStrategy::ALL.len());
+        assert!(templates.styles.contains(&"allman"));
+    }
+
+    #[test]
+    fn test_save_quine() {
+        let output = generate_output("test", None).unwrap();
+        let path = std::env::temp_dir().join(format!("psychoquine-save-{}.js", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+
+        let response = save_quine(path.clone(), "one-line".to_string(), output.clone());
+        assert_eq!(response.data.unwrap().bytes, output.one_line.len() + 1);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            output.source(OutputFormat::OneLine)
+        );
+        fs::remove_file(&path).unwrap();
+
+        let response = save_quine(path, "both".to_string(), output);
+        assert_eq!(response.error.unwrap().kind, "invalid_option");
+    }
 }
@@ -0,0 +1,120 @@
+//! Errors returned to the frontend
+//!
+//! Every failure carries a stable snake_case `kind` the UI can branch on:
+//! `QuineError::kind` for generation and verification failures, or one of
+//! the app's own kinds below. Variant-specific fields go in `details`.
+
+use std::fmt;
+use std::io;
+
+use psychoquine_core::{ParseOptionError, QuineError};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// An option had a value outside its vocabulary
+pub const INVALID_OPTION: &str = "invalid_option";
+/// A file could not be read or written
+pub const IO_ERROR: &str = "io";
+/// A history entry does not exist
+pub const NOT_FOUND: &str = "not_found";
+/// A history entry's input was too large to keep, so it cannot be reopened
+pub const INPUT_NOT_KEPT: &str = "input_not_kept";
+/// The background generation task failed
+pub const TASK_FAILED: &str = "task_failed";
+/// An input file is binary or not valid in its detected encoding
+pub const UNSUPPORTED_ENCODING: &str = "unsupported_encoding";
+
+/// A failed command, as the frontend receives it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommandError {
+    pub kind: &'static str,
+    pub message: String,
+    /// Fields such as `max` and `actual` for `input_too_large`, or
+    /// `option`, `value` and `expected` for `invalid_option`
+    pub details: Option<Value>,
+}
+
+impl CommandError {
+    pub fn new(kind: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    /// `value` is not one of the `expected` values of `option`
+    pub fn invalid_option(option: &str, value: &str, expected: &[&str]) -> Self {
+        Self {
+            kind: INVALID_OPTION,
+            message: format!(
+                "invalid value '{}' for {} (expected one of: {})",
+                value,
+                option,
+                expected.join(", ")
+            ),
+            details: Some(json!({ "option": option, "value": value, "expected": expected })),
+        }
+    }
+
+    pub fn io(context: impl fmt::Display, error: io::Error) -> Self {
+        Self::new(IO_ERROR, format!("{}: {}", context, error))
+    }
+}
+
+impl From<QuineError> for CommandError {
+    fn from(error: QuineError) -> Self {
+        let details = match &error {
+            QuineError::InputTooLarge { max, actual } => {
+                Some(json!({ "max": max, "actual": actual }))
+            }
+            _ => None,
+        };
+        Self {
+            kind: error.kind(),
+            message: error.to_string(),
+            details,
+        }
+    }
+}
+
+impl From<ParseOptionError> for CommandError {
+    fn from(error: ParseOptionError) -> Self {
+        Self::invalid_option(error.option, &error.value, &error.expected)
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quine_error_details() {
+        let error = CommandError::from(QuineError::InputTooLarge { max: 4, actual: 9 });
+        assert_eq!(error.kind, "input_too_large");
+        assert_eq!(error.details, Some(json!({ "max": 4, "actual": 9 })));
+
+        let error = CommandError::from(QuineError::EmptyInput);
+        assert_eq!(error.kind, "empty_input");
+        assert_eq!(error.details, None);
+    }
+
+    #[test]
+    fn test_serialized_shape() {
+        let error = CommandError::invalid_option("language", "cobol", &["javascript"]);
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({
+                "kind": "invalid_option",
+                "message": "invalid value 'cobol' for language (expected one of: javascript)",
+                "details": { "option": "language", "value": "cobol", "expected": ["javascript"] },
+            })
+        );
+    }
+}
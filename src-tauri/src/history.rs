@@ -0,0 +1,321 @@
+//! Generation history of the desktop app
+//!
+//! Each generation is appended to a JSON-lines file in the app data
+//! directory, one `HistoryEntry` per line. Pinning and deleting rewrite the
+//! file; beyond `MAX_UNPINNED` entries the oldest unpinned ones are dropped.
+//!
+//! Entries keep the input and options rather than the quines, which are
+//! regenerated when an entry is reopened, and inputs over
+//! `MAX_STORED_INPUT` bytes are not kept at all.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use psychoquine_core::{payload_hash, GenerateRequest, QuineOutput, QuineStats};
+use serde::{Deserialize, Serialize};
+
+/// Name of the history file in the app data directory
+pub const HISTORY_FILE_NAME: &str = "history.jsonl";
+
+/// Unpinned entries kept; pinned entries are never dropped
+pub const MAX_UNPINNED: usize = 200;
+
+/// Largest input kept in an entry; larger ones are listed but cannot be
+/// reopened
+pub const MAX_STORED_INPUT: usize = 1024 * 1024;
+
+/// Characters of input shown in `HistorySummary::preview`
+const PREVIEW_CHARS: usize = 80;
+
+/// One recorded generation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Never reused, even after the entry is deleted
+    pub id: u64,
+    /// Hex-encoded SHA-256 digest of the input
+    pub input_hash: String,
+    pub options: Option<GenerateRequest>,
+    pub stats: QuineStats,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub pinned: bool,
+    /// Start of the input
+    pub preview: String,
+    /// The input, or `None` when it was over `MAX_STORED_INPUT` bytes
+    pub input: Option<String>,
+}
+
+/// A history entry without its input, for listing
+#[derive(Debug, Clone, Serialize)]
+pub struct HistorySummary {
+    pub id: u64,
+    pub input_hash: String,
+    pub options: Option<GenerateRequest>,
+    pub stats: QuineStats,
+    pub timestamp: u64,
+    pub pinned: bool,
+    /// Start of the input
+    pub preview: String,
+}
+
+impl From<&HistoryEntry> for HistorySummary {
+    fn from(entry: &HistoryEntry) -> Self {
+        HistorySummary {
+            id: entry.id,
+            input_hash: entry.input_hash.clone(),
+            options: entry.options.clone(),
+            stats: entry.stats.clone(),
+            timestamp: entry.timestamp,
+            pinned: entry.pinned,
+            preview: entry.preview.clone(),
+        }
+    }
+}
+
+/// The history file and its entries, oldest first
+#[derive(Debug)]
+pub struct History {
+    path: PathBuf,
+    entries: Mutex<Vec<HistoryEntry>>,
+    /// Lowest id not handed out yet, updated with `entries` locked
+    next_id: AtomicU64,
+}
+
+impl History {
+    /// Load the history at `path`, creating its directory if needed
+    ///
+    /// Lines that don't parse, such as one cut short by a crash, are skipped.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut entries = Vec::new();
+        match File::open(&path) {
+            Ok(file) => {
+                for line in BufReader::new(file).lines() {
+                    if let Ok(entry) = serde_json::from_str::<HistoryEntry>(&line?) {
+                        entries.push(entry);
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        let next_id = entries.iter().map(|e| e.id + 1).max().unwrap_or(1);
+        Ok(History {
+            path,
+            entries: Mutex::new(entries),
+            next_id: AtomicU64::new(next_id),
+        })
+    }
+
+    /// Record a generation and return its entry
+    ///
+    /// Ids count up from the current time in milliseconds, so an id is not
+    /// handed out again after its entry is deleted, even by a later run.
+    pub fn record(
+        &self,
+        options: Option<GenerateRequest>,
+        output: &QuineOutput,
+    ) -> io::Result<HistoryEntry> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut entries = self.entries.lock().unwrap();
+        let id = self
+            .next_id
+            .load(Ordering::Relaxed)
+            .max(now.as_millis() as u64);
+        self.next_id.store(id + 1, Ordering::Relaxed);
+
+        let input = &output.original;
+        let entry = HistoryEntry {
+            id,
+            input_hash: payload_hash(input),
+            options,
+            stats: output.stats.clone(),
+            timestamp: now.as_secs(),
+            pinned: false,
+            preview: input.chars().take(PREVIEW_CHARS).collect(),
+            input: (input.len() <= MAX_STORED_INPUT).then(|| input.clone()),
+        };
+        entries.push(entry.clone());
+
+        let unpinned = entries.iter().filter(|e| !e.pinned).count();
+        if unpinned > MAX_UNPINNED {
+            let mut excess = unpinned - MAX_UNPINNED;
+            entries.retain(|e| {
+                let drop = excess > 0 && !e.pinned;
+                if drop {
+                    excess -= 1;
+                }
+                !drop
+            });
+            write_all(&self.path, &entries)?;
+        } else {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .read(true)
+                .append(true)
+                .open(&self.path)?;
+            // Finish a line torn by a crash, so this entry starts on its own
+            if !ends_with_newline(&mut file)? {
+                writeln!(file)?;
+            }
+            writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        }
+        Ok(entry)
+    }
+
+    /// Summaries of all entries, newest first
+    pub fn list(&self) -> Vec<HistorySummary> {
+        let entries = self.entries.lock().unwrap();
+        entries.iter().rev().map(HistorySummary::from).collect()
+    }
+
+    /// The entry with `id`, including its input
+    pub fn get(&self, id: u64) -> Option<HistoryEntry> {
+        let entries = self.entries.lock().unwrap();
+        entries.iter().find(|e| e.id == id).cloned()
+    }
+
+    /// Pin or unpin an entry, returning whether it exists
+    pub fn set_pinned(&self, id: u64, pinned: bool) -> io::Result<bool> {
+        let mut entries = self.entries.lock().unwrap();
+        let Some(entry) = entries.iter_mut().find(|e| e.id == id) else {
+            return Ok(false);
+        };
+        entry.pinned = pinned;
+        write_all(&self.path, &entries)?;
+        Ok(true)
+    }
+
+    /// Delete an entry, returning whether it existed
+    pub fn delete(&self, id: u64) -> io::Result<bool> {
+        let mut entries = self.entries.lock().unwrap();
+        let count = entries.len();
+        entries.retain(|e| e.id != id);
+        if entries.len() == count {
+            return Ok(false);
+        }
+        write_all(&self.path, &entries)?;
+        Ok(true)
+    }
+}
+
+/// Whether `file` is empty or ends with a newline
+fn ends_with_newline(file: &mut File) -> io::Result<bool> {
+    if file.metadata()?.len() == 0 {
+        return Ok(true);
+    }
+    let mut last = [0];
+    file.seek(SeekFrom::End(-1))?;
+    file.read_exact(&mut last)?;
+    Ok(last[0] == b'\n')
+}
+
+/// Replace the history file, through a temporary file so a crash never
+/// leaves it half written
+fn write_all(path: &Path, entries: &[HistoryEntry]) -> io::Result<()> {
+    let temp = path.with_extension("jsonl.tmp");
+    let mut file = io::BufWriter::new(File::create(&temp)?);
+    for entry in entries {
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    }
+    file.into_inner()?.sync_all()?;
+    fs::rename(&temp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use psychoquine_core::generate;
+
+    fn temp_history(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "psychoquine-history-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join(HISTORY_FILE_NAME)
+    }
+
+    #[test]
+    fn test_record_pin_delete_and_reload() {
+        let path = temp_history("reload");
+        let history = History::open(&path).unwrap();
+        let first = history.record(None, &generate("one").unwrap()).unwrap();
+        let second = history.record(None, &generate("two").unwrap()).unwrap();
+        assert_eq!(first.input_hash, payload_hash("one"));
+        assert!(second.id > first.id);
+
+        assert!(history.set_pinned(first.id, true).unwrap());
+        assert!(history.delete(second.id).unwrap());
+        assert!(!history.delete(second.id).unwrap());
+        assert!(!history.set_pinned(99, true).unwrap());
+
+        // A torn last line is skipped on load
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, "{{\"id\":").unwrap();
+
+        // A later run starts after the millisecond the deleted id came from
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let reloaded = History::open(&path).unwrap();
+        let list = reloaded.list();
+        assert_eq!(list.len(), 1);
+        assert!(list[0].pinned);
+        assert_eq!(list[0].preview, "one");
+        assert_eq!(reloaded.get(first.id).unwrap().input.unwrap(), "one");
+
+        // The next entry is not lost on the torn line, and the deleted
+        // newest id is not handed out again
+        let third = reloaded.record(None, &generate("three").unwrap()).unwrap();
+        assert!(third.id > second.id);
+        let list = History::open(&path).unwrap().list();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].id, third.id);
+        assert_eq!(list[0].preview, "three");
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_large_inputs_are_not_kept() {
+        let path = temp_history("large");
+        let history = History::open(&path).unwrap();
+        let input = "x".repeat(MAX_STORED_INPUT + 1);
+        let entry = history.record(None, &generate(&input).unwrap()).unwrap();
+        assert_eq!(entry.input, None);
+        assert_eq!(entry.preview.len(), PREVIEW_CHARS);
+        assert!(fs::metadata(&path).unwrap().len() < 1024);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_oldest_unpinned_entries_are_dropped() {
+        let path = temp_history("prune");
+        let history = History::open(&path).unwrap();
+        let output = generate("x").unwrap();
+        let pinned = history.record(None, &output).unwrap();
+        history.set_pinned(pinned.id, true).unwrap();
+        let first_unpinned = history.record(None, &output).unwrap();
+        for _ in 0..MAX_UNPINNED {
+            history.record(None, &output).unwrap();
+        }
+
+        let list = History::open(&path).unwrap().list();
+        assert_eq!(list.len(), MAX_UNPINNED + 1);
+        assert!(list.iter().any(|e| e.id == pinned.id));
+        // The first unpinned entry is gone
+        assert!(list.iter().all(|e| e.id != first_unpinned.id));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,181 @@
+//! Input files dropped on the window or picked in a file dialog
+//!
+//! A file is size-checked before it is read, then decoded from UTF-8,
+//! UTF-16 with a byte order mark, or Latin-1 when it is neither. Files
+//! with NUL bytes and no UTF-16 byte order mark are taken to be binary.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use psychoquine_core::QuineError;
+use serde::Serialize;
+
+use crate::error::{CommandError, UNSUPPORTED_ENCODING};
+
+/// Text encoding a file was decoded from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Encoding {
+    #[serde(rename = "utf-8")]
+    Utf8,
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+    #[serde(rename = "utf-16be")]
+    Utf16Be,
+    #[serde(rename = "latin-1")]
+    Latin1,
+}
+
+/// What the frontend is told about an input file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileInfo {
+    pub path: String,
+    /// File name without its directory
+    pub name: String,
+    /// Size on disk in bytes
+    pub bytes: usize,
+    pub encoding: Encoding,
+    /// Whether the file started with a byte order mark, which is dropped
+    pub bom: bool,
+    /// Last modification, in seconds since the Unix epoch
+    pub modified: Option<u64>,
+}
+
+/// Read and decode the file at `path`, refusing files over `max` bytes
+pub fn read_input_file(path: &Path, max: usize) -> Result<(String, FileInfo), CommandError> {
+    let context = format!("cannot read {}", path.display());
+    let file = File::open(path).map_err(|e| CommandError::io(&context, e))?;
+    let metadata = file.metadata().map_err(|e| CommandError::io(&context, e))?;
+    let size = usize::try_from(metadata.len()).unwrap_or(usize::MAX);
+    if size > max {
+        return Err(QuineError::InputTooLarge { max, actual: size }.into());
+    }
+
+    // The file may have grown since its size was checked
+    let mut bytes = Vec::with_capacity(size);
+    file.take(max.saturating_add(1) as u64)
+        .read_to_end(&mut bytes)
+        .map_err(|e| CommandError::io(&context, e))?;
+    if bytes.len() > max {
+        return Err(QuineError::InputTooLarge {
+            max,
+            actual: bytes.len(),
+        }
+        .into());
+    }
+
+    let (text, encoding, bom) = decode(&bytes).map_err(|message| {
+        CommandError::new(
+            UNSUPPORTED_ENCODING,
+            format!("{}: {}", path.display(), message),
+        )
+    })?;
+    let info = FileInfo {
+        path: path.display().to_string(),
+        name: path
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned()),
+        bytes: bytes.len(),
+        encoding,
+        bom,
+        modified: metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|elapsed| elapsed.as_secs()),
+    };
+    Ok((text, info))
+}
+
+/// Decode file contents, returning the text, its encoding and whether a
+/// byte order mark was dropped
+fn decode(bytes: &[u8]) -> Result<(String, Encoding, bool), &'static str> {
+    if let Some(rest) = bytes.strip_prefix(b"\xef\xbb\xbf") {
+        let text = String::from_utf8(rest.to_vec()).map_err(|_| "invalid UTF-8")?;
+        return Ok((text, Encoding::Utf8, true));
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xff\xfe") {
+        return Ok((
+            decode_utf16(rest, u16::from_le_bytes)?,
+            Encoding::Utf16Le,
+            true,
+        ));
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xfe\xff") {
+        return Ok((
+            decode_utf16(rest, u16::from_be_bytes)?,
+            Encoding::Utf16Be,
+            true,
+        ));
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return Ok((text.to_string(), Encoding::Utf8, false));
+    }
+    if bytes.contains(&0) {
+        return Err("looks like a binary file");
+    }
+    // Every byte is a Latin-1 character, so this cannot fail
+    let text = bytes.iter().map(|&byte| char::from(byte)).collect();
+    Ok((text, Encoding::Latin1, false))
+}
+
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> Result<String, &'static str> {
+    let pairs = bytes.chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return Err("invalid UTF-16: odd number of bytes");
+    }
+    let units: Vec<u16> = pairs.map(|pair| unit([pair[0], pair[1]])).collect();
+    String::from_utf16(&units).map_err(|_| "invalid UTF-16: unpaired surrogate")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode(b"hi").unwrap(), ("hi".into(), Encoding::Utf8, false));
+        assert_eq!(
+            decode(b"\xef\xbb\xbfhi").unwrap(),
+            ("hi".into(), Encoding::Utf8, true)
+        );
+        assert_eq!(
+            decode(b"\xff\xfeh\0\xac\x20").unwrap(),
+            ("h€".into(), Encoding::Utf16Le, true)
+        );
+        assert_eq!(
+            decode(b"\xfe\xff\0h").unwrap(),
+            ("h".into(), Encoding::Utf16Be, true)
+        );
+        assert_eq!(
+            decode(b"caf\xe9").unwrap(),
+            ("café".into(), Encoding::Latin1, false)
+        );
+        assert!(decode(b"\x89PNG\r\n\x1a\n\0\0").is_err());
+        assert!(decode(b"\xff\xfeh").is_err());
+    }
+
+    #[test]
+    fn test_read_input_file() {
+        let path =
+            std::env::temp_dir().join(format!("psychoquine-input-{}.txt", std::process::id()));
+        fs::write(&path, "hello").unwrap();
+
+        let (text, info) = read_input_file(&path, 5).unwrap();
+        assert_eq!(text, "hello");
+        assert_eq!(info.bytes, 5);
+        assert_eq!(info.encoding, Encoding::Utf8);
+        assert!(info.name.starts_with("psychoquine-input-"));
+        assert!(info.modified.is_some());
+
+        let error = read_input_file(&path, 4).unwrap_err();
+        assert_eq!(error.kind, "input_too_large");
+        fs::remove_file(&path).unwrap();
+
+        let error = read_input_file(&path, 5).unwrap_err();
+        assert_eq!(error.kind, "io");
+    }
+}
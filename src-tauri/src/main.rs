@@ -8,12 +8,44 @@
 )]
 
 mod commands;
+mod error;
+mod history;
+mod input;
 
-use commands::{generate_quine, get_version};
+use commands::{
+    cancel_generation, delete_history, generate_quine, generate_quine_async,
+    generate_quine_from_file, get_version, list_escape_strategies, list_history, list_templates,
+    pin_history, reopen_history, save_quine, verify_quine, Jobs,
+};
+use history::{History, HISTORY_FILE_NAME};
+use tauri::Manager;
 
 fn main() {
     tauri::Builder::default()
-        .invoke_handler(tauri::generate_handler![generate_quine, get_version])
+        .manage(Jobs::default())
+        .setup(|app| {
+            let dir = app
+                .path_resolver()
+                .app_data_dir()
+                .ok_or("cannot locate the app data directory")?;
+            app.manage(History::open(dir.join(HISTORY_FILE_NAME))?);
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            generate_quine,
+            generate_quine_async,
+            generate_quine_from_file,
+            cancel_generation,
+            get_version,
+            verify_quine,
+            list_templates,
+            list_escape_strategies,
+            save_quine,
+            list_history,
+            reopen_history,
+            pin_history,
+            delete_history
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
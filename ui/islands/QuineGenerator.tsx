@@ -14,16 +14,70 @@ interface QuineOutput {
   };
 }
 
+interface CommandError {
+  kind: string;
+  message: string;
+  details?: Record<string, unknown>;
+}
+
 interface GenerateResponse {
   success: boolean;
   data?: QuineOutput;
-  error?: string;
+  error?: CommandError;
 }
 
 // Check if running in Tauri
 const isTauri = typeof window !== "undefined" && "__TAURI__" in window;
 
-// Fallback quine generator for web-only mode
+// `psychoquine serve --allow-origin <this page's origin>` in web-only mode
+const API_URL = "http://127.0.0.1:7878";
+
+// Generate with the local API server, or null when it isn't running
+async function generateWithServer(
+  input: string,
+  escapeStrategy: string,
+): Promise<GenerateResponse | null> {
+  try {
+    const response = await fetch(`${API_URL}/generate`, {
+      method: "POST",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify({
+        input,
+        options: { escape_strategy: escapeStrategy },
+      }),
+    });
+    return await response.json();
+  } catch {
+    return null;
+  }
+}
+
+// The core engine compiled to WebAssembly by `deno task wasm`
+const WASM_MODULE = "/wasm/psychoquine_core.js";
+
+interface PsychoQuineWasm {
+  default(): Promise<unknown>;
+  generateWithOptions(
+    input: string,
+    options: Record<string, unknown>,
+  ): QuineOutput;
+}
+
+let wasmModule: Promise<PsychoQuineWasm | null> | null = null;
+
+// Load the WebAssembly engine once, or null when it hasn't been built
+function loadWasm(): Promise<PsychoQuineWasm | null> {
+  wasmModule ??= import(WASM_MODULE)
+    .then(async (module: PsychoQuineWasm) => {
+      await module.default();
+      return module;
+    })
+    .catch(() => null);
+  return wasmModule;
+}
+
+// Fallback quine generator for web-only mode when neither the API server
+// nor the WebAssembly engine is available
 function generateQuineFallback(input: string): QuineOutput {
   const escaped = input
     .replace(/\\/g, "\\\\")
@@ -45,7 +99,7 @@ function generateQuineFallback(input: string): QuineOutput {
     original: input,
     one_line: oneLine,
     multi_line: multiLine,
-    escape_strategy: "Standard",
+    escape_strategy: "standard",
     stats: {
       input_bytes: input.length,
       one_line_bytes: oneLine.length,
@@ -87,11 +141,30 @@ export default function QuineGenerator() {
         if (response.success && response.data) {
           output.value = response.data;
         } else {
-          error.value = response.error || "Unknown error occurred";
+          error.value = response.error?.message || "Unknown error occurred";
         }
       } else {
-        // Web fallback
-        output.value = generateQuineFallback(input.value);
+        const response = await generateWithServer(
+          input.value,
+          escapeStrategy.value,
+        );
+        const wasm = response ? null : await loadWasm();
+        if (wasm) {
+          try {
+            output.value = wasm.generateWithOptions(input.value, {
+              escape_strategy: escapeStrategy.value,
+            });
+          } catch (failure) {
+            // The engine throws `{ kind, message }`
+            error.value = (failure as CommandError).message;
+          }
+        } else if (!response) {
+          output.value = generateQuineFallback(input.value);
+        } else if (response.success && response.data) {
+          output.value = response.data;
+        } else {
+          error.value = response.error?.message || "Unknown error occurred";
+        }
       }
     } catch (err) {
       error.value = err instanceof Error